@@ -1,3 +1,4 @@
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -17,14 +18,77 @@ fn calc_fuel_integrated(mass: i64) -> i64 {
     return fuel;
 }
 
+// Parse the module masses, one per line, skipping blank lines.
+fn parse_masses<I: Iterator<Item = String>>(lines: I) -> Result<Vec<i64>, String> {
+    let mut masses = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mass = line
+            .parse::<i64>()
+            .map_err(|_| format!("Invalid mass on line {}: {}", i + 1, line))?;
+        masses.push(mass);
+    }
+
+    Ok(masses)
+}
+
+// Sum the fuel required for each module, returning the simple total for
+// part 1 and the total including the fuel's own mass for part 2.
+fn total_fuel<I: Iterator<Item = i64>>(masses: I) -> (i64, i64) {
+    masses.fold((0, 0), |(simple, integrated), mass| {
+        (
+            simple + calc_fuel_simple(mass),
+            integrated + calc_fuel_integrated(mass),
+        )
+    })
+}
+
 fn main() {
-    let file = File::open("input").unwrap();
+    let filename = env::args().nth(1).unwrap_or_else(|| String::from("input"));
+    let file = File::open(filename).unwrap();
     let reader = BufReader::new(file);
-    let mut total = 0;
-    for line in reader.lines() {
-        let mass = line.unwrap().parse::<i64>().unwrap();
-        total += calc_fuel_integrated(mass);
+    let lines = reader.lines().map(|line| line.unwrap());
+    let masses = match parse_masses(lines) {
+        Ok(masses) => masses,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let (simple, integrated) = total_fuel(masses.into_iter());
+
+    println!("Part 1: {}", simple);
+    println!("Part 2: {}", integrated);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuel() {
+        let masses = vec![12, 14, 1969, 100756];
+        assert_eq!(total_fuel(masses.into_iter()), (34241, 51316));
     }
 
-    println!("{}", total);
+    #[test]
+    fn parse() {
+        let filename = env::temp_dir().join("day1_parse_masses");
+        std::fs::write(&filename, "12\n\n1969\n").unwrap();
+        let file = File::open(&filename).unwrap();
+        let lines = BufReader::new(file).lines().map(|line| line.unwrap());
+        let masses = parse_masses(lines);
+        std::fs::remove_file(&filename).unwrap();
+        assert_eq!(masses, Ok(vec![12, 1969]));
+
+        let lines = vec!["12", "", "abc"].into_iter().map(String::from);
+        assert_eq!(
+            parse_masses(lines),
+            Err(String::from("Invalid mass on line 3: abc"))
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use num_integer;
 use std::collections::HashSet;
+use std::env;
 use std::f64;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -83,6 +84,12 @@ impl Map {
         return asteroids;
     }
 
+    // The number of asteroids vaporized by the first full rotation of a laser
+    // at the given location - every asteroid visible from it.
+    fn vaporized_per_rotation(&self, laser: (i32, i32)) -> usize {
+        self.find_visible_asteroids(laser).len()
+    }
+
     fn vaporize_asteroids(&mut self, asteroids: &[(i32, i32)]) {
         for location in asteroids {
             self.asteroids.remove(location);
@@ -92,12 +99,18 @@ impl Map {
 
 // Find the location on a map that can see the most asteroids.
 // Return that location, plus the number of asteroids that can be
-// seen from it.
+// seen from it. Ties are broken by picking the location with the
+// smallest (y, x).
 fn find_optimal_monitoring_location(map: &Map) -> ((i32, i32), u32) {
     let mut max_asteroids = 0;
     let mut best_space = (0, 0);
 
-    for src in &map.asteroids {
+    // Visit the asteroids in a fixed order rather than hash order, so the
+    // first of any equally good locations always wins.
+    let mut candidates: Vec<&(i32, i32)> = map.asteroids.iter().collect();
+    candidates.sort_by_key(|(x, y)| (*y, *x));
+
+    for src in candidates {
         let asteroids = map.find_visible_asteroids(*src);
         if asteroids.len() > max_asteroids {
             max_asteroids = asteroids.len();
@@ -148,12 +161,28 @@ fn find_nth_vaporized(m: &Map, laser_loc: (i32, i32), n: usize) -> (i32, i32) {
 }
 
 fn main() {
+    let filename = env::args()
+        .skip_while(|a| a != "--map-file")
+        .nth(1)
+        .unwrap_or_else(|| String::from("input"));
+
     // Part 1
-    let map = Map::from_file("input");
+    let map = Map::from_file(&filename);
     let (coords, count) = find_optimal_monitoring_location(&map);
     println!("Best location {:?} sees {} asteroids", coords, count);
 
     // Part 2
+    println!(
+        "First rotation vaporizes {} asteroids",
+        map.vaporized_per_rotation(coords)
+    );
+
+    // The example maps may not have enough asteroids for part 2 - the station
+    // itself doesn't count.
+    if map.asteroids.len() <= TARGET_VAPORIZE_COUNT {
+        println!("Fewer than {} asteroids to vaporize", TARGET_VAPORIZE_COUNT);
+        return;
+    }
     let result = find_nth_vaporized(&map, coords, TARGET_VAPORIZE_COUNT);
     println!(
         "Vaporized asteroid number {}: {:?}. Answer {}",
@@ -182,6 +211,21 @@ mod tests {
         assert!(diff < 1e-10);
     }
 
+    #[test]
+    fn pt1_ties() {
+        let strs = vec![
+            String::from("..#"),
+            String::from("..."),
+            String::from("#.."),
+        ];
+        let map = Map::from_strings(&strs);
+        assert_eq!(find_optimal_monitoring_location(&map), ((2, 0), 1));
+
+        let strs = vec![String::from("#.#")];
+        let map = Map::from_strings(&strs);
+        assert_eq!(find_optimal_monitoring_location(&map), ((0, 0), 1));
+    }
+
     #[test]
     fn pt1_example_1() {
         let strs = vec![
@@ -355,4 +399,37 @@ mod tests {
             assert_eq!(coords, exp_coords);
         }
     }
+
+    #[test]
+    fn pt2_rotation_count() {
+        let strs = vec![
+            String::from(".#..##.###...#######"),
+            String::from("##.############..##."),
+            String::from(".#.######.########.#"),
+            String::from(".###.#######.####.#."),
+            String::from("#####.##.#.##.###.##"),
+            String::from("..#####..#.#########"),
+            String::from("####################"),
+            String::from("#.####....###.#.#.##"),
+            String::from("##.#################"),
+            String::from("#####.##.###..####.."),
+            String::from("..######..##.#######"),
+            String::from("####.##.####...##..#"),
+            String::from(".#####..#.######.###"),
+            String::from("##...#.##########..."),
+            String::from("#.##########.#######"),
+            String::from(".####.#.###.###.#.##"),
+            String::from("....##.##.###..#####"),
+            String::from(".#.#.###########.###"),
+            String::from("#.#.#.#####.####.###"),
+            String::from("###.##.####.##.#..##"),
+        ];
+
+        let map = Map::from_strings(&strs);
+        let (station_coords, _) = find_optimal_monitoring_location(&map);
+
+        // The station can see 210 asteroids, so the 200th vaporization
+        // happens during the first rotation.
+        assert_eq!(map.vaporized_per_rotation(station_coords), 210);
+    }
 }
@@ -2,6 +2,9 @@ extern crate intcode;
 
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::Write;
 
 const BLACK: u8 = 0;
 const WHITE: u8 = 1;
@@ -80,19 +83,25 @@ fn run_paint_robot(filename: &str, start_color: u8) -> HashMap<(i64, i64), u8> {
     return hull.into_inner();
 }
 
-fn robot_output_to_file(output: &HashMap<(i64, i64), u8>, filename: &str) {
-    // Find the bounds of the image
+// Find the bounds of the painted area, returns (min_x, max_x, min_y, max_y).
+fn find_bounds(output: &HashMap<(i64, i64), u8>) -> (i64, i64, i64, i64) {
     let mut min_x: i64 = 0;
     let mut max_x: i64 = 0;
     let mut min_y: i64 = 0;
     let mut max_y: i64 = 0;
-    for ((x, y), _) in output {
+    for (x, y) in output.keys() {
         min_x = std::cmp::min(*x, min_x);
         max_x = std::cmp::max(*x, max_x);
         min_y = std::cmp::min(*y, min_y);
         max_y = std::cmp::max(*y, max_y);
     }
 
+    (min_x, max_x, min_y, max_y)
+}
+
+fn robot_output_to_file(output: &HashMap<(i64, i64), u8>, filename: &str) {
+    let (min_x, max_x, min_y, max_y) = find_bounds(output);
+
     let width = (max_x - min_x) as u32;
     let height = (max_y - min_y) as u32;
 
@@ -114,7 +123,132 @@ fn robot_output_to_file(output: &HashMap<(i64, i64), u8>, filename: &str) {
     buf.save(filename).unwrap();
 }
 
+// Render the robot output as lines of text, with '#' for white panels.
+fn render_hull(output: &HashMap<(i64, i64), u8>) -> String {
+    let (min_x, max_x, min_y, max_y) = find_bounds(output);
+
+    (min_y..=max_y)
+        .map(|y| {
+            (min_x..=max_x)
+                .map(|x| match output.get(&(x, y)) {
+                    Some(&WHITE) => '#',
+                    _ => ' ',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Write the robot output as an SVG, with one square per white panel, so the
+// registration identifier can be rendered at any scale.
+fn robot_output_to_svg(output: &HashMap<(i64, i64), u8>, filename: &str) {
+    let (min_x, max_x, min_y, max_y) = find_bounds(output);
+    let width = max_x - min_x + 1;
+    let height = max_y - min_y + 1;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\" style=\"background:black\">\n",
+        w = width,
+        h = height
+    );
+
+    // Sort the panels so the output is stable between runs.
+    let mut white_panels: Vec<(i64, i64)> = output
+        .iter()
+        .filter(|(_, &color)| color == WHITE)
+        .map(|(&coords, _)| coords)
+        .collect();
+    white_panels.sort();
+
+    for (x, y) in white_panels {
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"white\"/>\n",
+            x - min_x,
+            y - min_y
+        ));
+    }
+    svg.push_str("</svg>\n");
+
+    let mut file = File::create(filename).expect("Failed to create file");
+    file.write_all(svg.as_bytes())
+        .expect("Failed to write file");
+}
+
+// Both parts run the same program from the input file, and differ only in the
+// colour of the starting panel. The panel count is for part 1, which starts on
+// black, while the rendered hull uses the colour given by --start, defaulting
+// to white for part 2.
 fn main() {
-    let robot_output = run_paint_robot("input", WHITE);
+    let start_color = env::args()
+        .skip_while(|a| a != "--start")
+        .nth(1)
+        .map_or(WHITE, |c| match c.as_str() {
+            "black" => BLACK,
+            "white" => WHITE,
+            _ => panic!("Invalid start color {}, expected black or white", c),
+        });
+
+    // Part 1: every panel in the robot output has been painted at least once.
+    let robot_output = run_paint_robot("input", BLACK);
+    println!("Panels painted: {}", robot_output.len());
+
+    // Part 2
+    let robot_output = run_paint_robot("input", start_color);
     robot_output_to_file(&robot_output, "output.png");
+    robot_output_to_svg(&robot_output, "output.svg");
+    println!("{}", render_hull(&robot_output));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn panels_painted() {
+        // Paint the current panel white and turn left, then paint the next
+        // panel black and turn left again.
+        let path = std::env::temp_dir().join("day11_panels_painted");
+        let filename = path.to_str().unwrap();
+        fs::write(filename, "104,1,104,0,104,0,104,0,99\n").unwrap();
+        let hull = run_paint_robot(filename, BLACK);
+        let _ = fs::remove_file(filename);
+
+        assert_eq!(hull.len(), 2);
+        assert_eq!(hull.get(&(0, 0)), Some(&WHITE));
+        assert_eq!(hull.get(&(-1, 0)), Some(&BLACK));
+    }
+
+    #[test]
+    fn render() {
+        let mut hull = HashMap::new();
+        hull.insert((0, 0), WHITE);
+        hull.insert((1, 0), BLACK);
+        hull.insert((-1, 1), WHITE);
+        hull.insert((1, 1), WHITE);
+
+        assert_eq!(render_hull(&hull), " # \n# #");
+    }
+
+    #[test]
+    fn svg_output() {
+        let mut hull = HashMap::new();
+        hull.insert((0, 0), WHITE);
+        hull.insert((1, 0), BLACK);
+        hull.insert((-1, 2), WHITE);
+        hull.insert((3, -1), WHITE);
+
+        let path = std::env::temp_dir().join("day11_svg_output.svg");
+        let filename = path.to_str().unwrap();
+        robot_output_to_svg(&hull, filename);
+        let svg = fs::read_to_string(filename).unwrap();
+        let _ = fs::remove_file(filename);
+
+        // One rect per white panel, on a black background.
+        let white = hull.values().filter(|&&c| c == WHITE).count();
+        assert_eq!(svg.matches("<rect").count(), white);
+        assert!(svg.contains("style=\"background:black\""));
+        assert!(svg.contains("viewBox=\"0 0 5 4\""));
+    }
 }
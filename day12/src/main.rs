@@ -1,4 +1,6 @@
 use cgmath::Vector3;
+use std::env;
+use std::fs;
 
 const SIM_STEPS: u64 = 1000;
 
@@ -15,6 +17,66 @@ impl Moon {
             velocity: Vector3::new(0, 0, 0),
         }
     }
+
+    fn with_velocity(position: Vector3<i64>, velocity: Vector3<i64>) -> Moon {
+        Moon { position, velocity }
+    }
+}
+
+// Parse a vector of the form "<x=1, y=2, z=3>".
+fn parse_vector(s: &str) -> Option<Vector3<i64>> {
+    let s = s.trim().strip_prefix('<')?.strip_suffix('>')?;
+    let mut components = s.split(',').map(|c| {
+        let (_, val) = c.trim().split_at(c.trim().find('=')? + 1);
+        val.parse::<i64>().ok()
+    });
+
+    let x = components.next()??;
+    let y = components.next()??;
+    let z = components.next()??;
+    if components.next().is_some() {
+        return None;
+    }
+
+    Some(Vector3::new(x, y, z))
+}
+
+// Parse a moon, either as a position-only line "<x=1, y=2, z=3>" as given
+// in the puzzle input, or with a velocity as well, e.g.
+// "pos=<x=1, y=2, z=3>, vel=<x=0, y=-1, z=0>".
+fn parse_moon(line: &str) -> Option<Moon> {
+    let line = line.trim();
+    match line.find("vel=") {
+        Some(vel_idx) => {
+            let pos = line[..vel_idx].trim().trim_end_matches(',');
+            let pos = pos.trim().strip_prefix("pos=").unwrap_or(pos);
+            let vel = &line[vel_idx + "vel=".len()..];
+            Some(Moon::with_velocity(parse_vector(pos)?, parse_vector(vel)?))
+        }
+        None => {
+            let pos = line.strip_prefix("pos=").unwrap_or(line);
+            let pos = parse_vector(pos)?;
+            Some(Moon::new(pos.x, pos.y, pos.z))
+        }
+    }
+}
+
+// Parse one moon per line, ignoring blank lines.
+fn parse_moons(lines: &[String]) -> Vec<Moon> {
+    lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| parse_moon(l).unwrap_or_else(|| panic!("Invalid moon: {}", l)))
+        .collect()
+}
+
+fn moons_from_file(filename: &str) -> Vec<Moon> {
+    let lines: Vec<String> = fs::read_to_string(filename)
+        .expect("Failed to read file")
+        .lines()
+        .map(String::from)
+        .collect();
+    parse_moons(&lines)
 }
 
 fn gcd(a: u64, b: u64) -> u64 {
@@ -82,64 +144,47 @@ fn calc_energy(moons: &Vec<Moon>) -> u64 {
         .sum();
 }
 
-fn find_repeats(orig_moons: &Vec<Moon>) -> u64 {
-    let mut moons = orig_moons.clone();
+// Each axis is simulated independently of the others, so find the period of
+// each axis and combine them.
+fn find_repeats(orig_moons: &[Moon]) -> u64 {
+    const AXES: usize = 3;
 
-    let mut i: u64 = 0;
+    let mut moons = orig_moons.to_vec();
+    let mut repeats: Vec<Option<u64>> = vec![None; AXES];
 
-    let mut x_repeat = None;
-    let mut y_repeat = None;
-    let mut z_repeat = None;
-    loop {
+    let mut i: u64 = 0;
+    while repeats.iter().any(Option::is_none) {
         i += 1;
         run_sim(&mut moons, 1);
 
-        let (x_match, y_match, z_match) =
-            moons
-                .iter()
-                .zip(orig_moons.iter())
-                .fold((true, true, true), |acc, (m1, m2)| {
-                    let x =
-                        acc.0 && m1.position.x == m2.position.x && m1.velocity.x == m2.velocity.x;
-                    let y =
-                        acc.1 && m1.position.y == m2.position.y && m1.velocity.y == m2.velocity.y;
-                    let z =
-                        acc.2 && m1.position.z == m2.position.z && m1.velocity.z == m2.velocity.z;
-
-                    (x, y, z)
-                });
-
-        if x_repeat.is_none() && x_match {
-            x_repeat = Some(i);
-        }
-        if y_repeat.is_none() && y_match {
-            y_repeat = Some(i);
-        }
-        if z_repeat.is_none() && z_match {
-            z_repeat = Some(i);
-        }
+        for (axis, repeat) in repeats.iter_mut().enumerate() {
+            let axis_match = moons.iter().zip(orig_moons).all(|(m1, m2)| {
+                m1.position[axis] == m2.position[axis] && m1.velocity[axis] == m2.velocity[axis]
+            });
 
-        if x_repeat.is_some() && y_repeat.is_some() && z_repeat.is_some() {
-            break;
+            if repeat.is_none() && axis_match {
+                *repeat = Some(i);
+            }
         }
     }
 
-    return lcm(x_repeat.unwrap(), lcm(y_repeat.unwrap(), z_repeat.unwrap()));
+    let periods: Vec<u64> = repeats.into_iter().map(Option::unwrap).collect();
+    periods.into_iter().fold(1, lcm)
 }
 
 fn main() {
-    let moons = vec![
-        Moon::new(9, 13, -8),
-        Moon::new(-3, 16, -17),
-        Moon::new(-4, 11, -10),
-        Moon::new(0, -2, -2),
-    ];
+    // The input may also be a saved state, with velocities.
+    let filename = env::args().nth(1).unwrap_or_else(|| String::from("input"));
+    let moons = moons_from_file(&filename);
+    let steps = env::args()
+        .nth(2)
+        .map_or(SIM_STEPS, |s| s.parse::<u64>().expect("Invalid step count"));
 
     // Part 1
     let mut sim_moons = moons.clone();
-    run_sim(&mut sim_moons, SIM_STEPS);
+    run_sim(&mut sim_moons, steps);
     let energy = calc_energy(&sim_moons);
-    println!("Total energy after {} steps: {}", SIM_STEPS, energy);
+    println!("Total energy after {} steps: {}", steps, energy);
 
     // Part 2
     let period = find_repeats(&moons);
@@ -175,6 +220,44 @@ mod tests {
         assert_eq!(energy, 179);
     }
 
+    #[test]
+    fn parse() {
+        let moon = parse_moon("pos=<x=2, y=-1, z=1>, vel=<x=3, y=-1, z=-1>").unwrap();
+        assert_eq!(moon.position, Vector3::new(2, -1, 1));
+        assert_eq!(moon.velocity, Vector3::new(3, -1, -1));
+
+        let moon = parse_moon("<x=-1, y=0, z=2>").unwrap();
+        assert_eq!(moon.position, Vector3::new(-1, 0, 2));
+        assert_eq!(moon.velocity, Vector3::new(0, 0, 0));
+    }
+
+    #[test]
+    fn parse_input() {
+        let lines: Vec<String> = vec![
+            "<x=-1, y=0, z=2>",
+            "<x=2, y=-10, z=-7>",
+            "<x=4, y=-8, z=8>",
+            "<x=3, y=5, z=-1>",
+            "",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        let moons = parse_moons(&lines);
+        let positions: Vec<Vector3<i64>> = moons.iter().map(|m| m.position).collect();
+        assert_eq!(
+            positions,
+            vec![
+                Vector3::new(-1, 0, 2),
+                Vector3::new(2, -10, -7),
+                Vector3::new(4, -8, 8),
+                Vector3::new(3, 5, -1),
+            ]
+        );
+        assert!(moons.iter().all(|m| m.velocity == Vector3::new(0, 0, 0)));
+    }
+
     #[test]
     fn pt2() {
         let moons = vec![
@@ -9,7 +9,9 @@ use ggez::{Context, GameResult};
 use intcode::Program;
 use std::cell::Cell;
 use std::cmp::max;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fs;
 
 const SCREEN_WIDTH: f32 = 800.0;
 const SCREEN_HEIGHT: f32 = 600.0;
@@ -52,10 +54,81 @@ enum OutputMode {
     Score,
 }
 
+// A complete instruction from the program's output.
+enum Output {
+    Tile((i64, i64), i64),
+    Score(i64),
+}
+
+// Decodes the program's output, which is a sequence of (x, y, tile) triples,
+// where x = -1, y = 0 gives the score instead of a tile.
+struct OutputDecoder {
+    x: i64,
+    y: i64,
+    mode: OutputMode,
+}
+
+impl OutputDecoder {
+    fn new() -> Self {
+        OutputDecoder {
+            x: 0,
+            y: 0,
+            mode: OutputMode::SetX,
+        }
+    }
+
+    // Take the next output value, returning the decoded output once a triple
+    // is complete.
+    fn push(&mut self, val: i64) -> Option<Output> {
+        match self.mode {
+            OutputMode::SetX => {
+                self.x = val;
+                self.mode = OutputMode::SetY;
+                None
+            }
+            OutputMode::SetY => {
+                self.y = val;
+
+                if self.x == -1 && self.y == 0 {
+                    self.mode = OutputMode::Score;
+                } else {
+                    self.mode = OutputMode::Draw;
+                }
+                None
+            }
+            OutputMode::Draw => {
+                self.mode = OutputMode::SetX;
+                Some(Output::Tile((self.x, self.y), val))
+            }
+            OutputMode::Score => {
+                self.mode = OutputMode::SetX;
+                Some(Output::Score(val))
+            }
+        }
+    }
+}
+
+// Count the block tiles left on screen by a sequence of program output.
+fn count_blocks(outputs: &[i64]) -> usize {
+    let mut decoder = OutputDecoder::new();
+    let mut screen = HashMap::new();
+    for &val in outputs {
+        if let Some(Output::Tile(coords, tile)) = decoder.push(val) {
+            screen.insert(coords, tile);
+        }
+    }
+
+    screen.values().filter(|&&tile| tile == TILE_BLOCK).count()
+}
+
 struct Game {
     program: Program,
     screen: HashMap<(i64, i64), i64>,
     score: i64,
+    // Every input given to the program so far.
+    inputs: Vec<i64>,
+    // Inputs to give instead of following the ball, when replaying.
+    replay: Option<VecDeque<i64>>,
 }
 
 impl Game {
@@ -66,12 +139,19 @@ impl Game {
         program.poke(0, 2);
 
         Game {
-            program: program,
+            program,
             screen: HashMap::new(),
             score: 0,
+            inputs: Vec::new(),
+            replay: None,
         }
     }
 
+    // Replay a recorded sequence of inputs rather than following the ball.
+    fn set_replay(&mut self, inputs: Vec<i64>) {
+        self.replay = Some(inputs.into_iter().collect());
+    }
+
     fn find_unique_tile(&self, find_type: i64) -> Option<(i64, i64)> {
         assert!(find_type == TILE_BALL || find_type == TILE_PADDLE);
         for (coords, tile_type) in self.screen.clone() {
@@ -82,6 +162,98 @@ impl Game {
 
         None
     }
+
+    // Run the program until it asks for an input, updating the screen and
+    // score from its output, and moving the paddle towards the ball.
+    // Returns false once the program has stopped.
+    fn advance(&mut self) -> bool {
+        let mut decoder = OutputDecoder::new();
+        let mut screen = self.screen.clone();
+        let mut score = self.score;
+
+        let ball_loc_ref = Cell::new(self.find_unique_tile(TILE_BALL));
+        let paddle_loc_ref = Cell::new(self.find_unique_tile(TILE_PADDLE));
+
+        let inputs = &mut self.inputs;
+        let replay = &mut self.replay;
+
+        let mut done = false;
+        let mut result: Result<(), _> = Ok(());
+        while !done && result.is_ok() {
+            result = self.program.step(
+                &mut || {
+                    let input = match (replay.as_mut(), ball_loc_ref.get(), paddle_loc_ref.get()) {
+                        (Some(replay), _, _) => {
+                            replay.pop_front().expect("Replay ran out of inputs")
+                        }
+                        (None, Some((ball_x, _)), Some((paddle_x, _))) => {
+                            decide_input(ball_x, paddle_x)
+                        }
+                        _ => INPUT_NEUTRAL,
+                    };
+
+                    inputs.push(input);
+                    done = true;
+                    input
+                },
+                &mut |val| match decoder.push(val) {
+                    Some(Output::Tile(coords, tile)) => {
+                        screen.insert(coords, tile);
+
+                        match tile {
+                            TILE_BALL => ball_loc_ref.set(Some(coords)),
+                            TILE_PADDLE => paddle_loc_ref.set(Some(coords)),
+                            _ => (),
+                        };
+                    }
+                    Some(Output::Score(val)) => score = val,
+                    None => (),
+                },
+            );
+        }
+
+        self.screen = screen;
+        self.score = score;
+
+        result.is_ok()
+    }
+
+    // Play the game to completion without drawing anything, returning the
+    // final score.
+    fn run_headless(&mut self) -> i64 {
+        while self.advance() {}
+        self.score
+    }
+}
+
+// Move the paddle to follow the ball.
+fn decide_input(ball_x: i64, paddle_x: i64) -> i64 {
+    if ball_x > paddle_x {
+        INPUT_RIGHT
+    } else if ball_x < paddle_x {
+        INPUT_LEFT
+    } else {
+        INPUT_NEUTRAL
+    }
+}
+
+// Read a recorded sequence of inputs, one per line.
+fn read_inputs(filename: &str) -> Vec<i64> {
+    fs::read_to_string(filename)
+        .expect("Failed to read replay file")
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            l.trim()
+                .parse::<i64>()
+                .expect("Invalid input in replay file")
+        })
+        .collect()
+}
+
+fn write_inputs(filename: &str, inputs: &[i64]) {
+    let lines: Vec<String> = inputs.iter().map(|i| i.to_string()).collect();
+    fs::write(filename, lines.join("\n") + "\n").expect("Failed to write record file");
 }
 
 impl event::EventHandler for Game {
@@ -93,84 +265,16 @@ impl event::EventHandler for Game {
         }
 
         if timer::check_update_time(ctx, DESIRED_FPS) {
-            let mut x = 0;
-            let mut y = 0;
-            let mut output_mode = OutputMode::SetX;
-            let mut screen = self.screen.clone();
-            let mut score = self.score;
-
-            let ball_loc_ref = Cell::new(self.find_unique_tile(TILE_BALL));
-            let paddle_loc_ref = Cell::new(self.find_unique_tile(TILE_PADDLE));
-
             // Run the program until it asks for an input, give the input,
             // then take a break to do some drawing.
-            let mut done = false;
-            let mut result: Result<(), _> = Ok(());
-            while !done && result.is_ok() {
-                result = self.program.step(
-                    &mut || {
-                        let ball_coords = ball_loc_ref.get();
-                        let paddle_coords = paddle_loc_ref.get();
-
-                        let input = match (ball_coords, paddle_coords) {
-                            (Some((ball_x, _)), Some((paddle_x, _))) => {
-                                if ball_x > paddle_x {
-                                    INPUT_RIGHT
-                                } else if ball_x < paddle_x {
-                                    INPUT_LEFT
-                                } else {
-                                    INPUT_NEUTRAL
-                                }
-                            }
-                            _ => INPUT_NEUTRAL,
-                        };
+            let prev_score = self.score;
+            let running = self.advance();
 
-                        done = true;
-                        input
-                    },
-                    &mut |val| {
-                        match output_mode {
-                            OutputMode::SetX => {
-                                x = val;
-                                output_mode = OutputMode::SetY;
-                            }
-                            OutputMode::SetY => {
-                                y = val;
-
-                                if x == -1 && y == 0 {
-                                    output_mode = OutputMode::Score;
-                                } else {
-                                    output_mode = OutputMode::Draw;
-                                }
-                            }
-                            OutputMode::Draw => {
-                                screen.insert((x, y), val);
-
-                                match val {
-                                    TILE_BALL => ball_loc_ref.set(Some((x, y))),
-                                    TILE_PADDLE => paddle_loc_ref.set(Some((x, y))),
-                                    _ => (),
-                                };
-
-                                output_mode = OutputMode::SetX;
-                            }
-                            OutputMode::Score => {
-                                score = val;
-                                output_mode = OutputMode::SetX;
-                            }
-                        };
-                    },
-                );
+            if self.score != prev_score {
+                println!("Score: {}", self.score);
             }
 
-            self.screen = screen;
-
-            if score != self.score {
-                println!("Score: {}", score);
-                self.score = score;
-            }
-
-            if result.is_err() {
+            if !running {
                 event::quit(ctx);
             }
         }
@@ -218,10 +322,71 @@ impl event::EventHandler for Game {
 }
 
 fn main() -> GameResult {
-    let cb = ggez::ContextBuilder::new("AOC19 - Day 13", "juzley")
-        .window_setup(ggez::conf::WindowSetup::default().title("Breakout!"))
-        .window_mode(ggez::conf::WindowMode::default().dimensions(SCREEN_WIDTH, SCREEN_HEIGHT));
-    let (ctx, events_loop) = &mut cb.build().unwrap();
-    let game = &mut Game::new("input");
-    event::run(ctx, events_loop, game)
+    // Part 1: run the program without freeplay mode, and count the blocks it
+    // draws.
+    let mut outputs = Vec::new();
+    Program::from_file("input").execute_ex(|| INPUT_NEUTRAL, |val| outputs.push(val));
+    println!("Blocks: {}", count_blocks(&outputs));
+
+    let record_file = env::args().skip_while(|a| a != "--record").nth(1);
+    let replay_file = env::args().skip_while(|a| a != "--replay").nth(1);
+
+    let mut game = Game::new("input");
+    if let Some(filename) = replay_file {
+        game.set_replay(read_inputs(&filename));
+    }
+
+    let result = if env::args().any(|a| a == "--headless") {
+        let score = game.run_headless();
+        println!("Final score: {}", score);
+        Ok(())
+    } else {
+        let cb = ggez::ContextBuilder::new("AOC19 - Day 13", "juzley")
+            .window_setup(ggez::conf::WindowSetup::default().title("Breakout!"))
+            .window_mode(ggez::conf::WindowMode::default().dimensions(SCREEN_WIDTH, SCREEN_HEIGHT));
+        let (ctx, events_loop) = &mut cb.build().unwrap();
+        event::run(ctx, events_loop, &mut game)
+    };
+
+    if let Some(filename) = record_file {
+        write_inputs(&filename, &game.inputs);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks() {
+        let outputs = vec![
+            0, 0, TILE_BLOCK, // Block
+            1, 0, TILE_BLOCK, // Block
+            0, 0, TILE_WALL, // Block replaced with a wall
+            -1, 0, TILE_BLOCK, // Score, not a block
+            2, 0, TILE_BLOCK, // Block
+            3, 0, TILE_BALL, // Ball
+        ];
+        assert_eq!(count_blocks(&outputs), 2);
+    }
+
+    #[test]
+    fn record_replay() {
+        let mut game = Game::new("input");
+        let score = game.run_headless();
+
+        let path = std::env::temp_dir().join("day13_record_replay");
+        let filename = path.to_str().unwrap();
+        write_inputs(filename, &game.inputs);
+        let inputs = read_inputs(filename);
+        let _ = fs::remove_file(filename);
+        assert_eq!(inputs, game.inputs);
+
+        let mut replay = Game::new("input");
+        replay.set_replay(inputs);
+        assert_eq!(replay.run_headless(), score);
+        assert_eq!(replay.inputs, game.inputs);
+    }
 }
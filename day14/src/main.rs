@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -17,7 +18,20 @@ fn calc_ore(reactions: &ReactionMap) -> u64 {
 }
 
 fn calc_ore_for_fuel(fuel: u64, reactions: &ReactionMap) -> u64 {
+    run_reactions(fuel, reactions).0
+}
+
+// Find how many times each chemical's reaction runs to produce the given
+// amount of fuel.
+fn reaction_counts(fuel: u64, reactions: &ReactionMap) -> HashMap<String, u64> {
+    run_reactions(fuel, reactions).1
+}
+
+// Work out the reactions needed to produce the given amount of fuel. Returns
+// the ore required, and the number of times each chemical's reaction runs.
+fn run_reactions(fuel: u64, reactions: &ReactionMap) -> (u64, HashMap<String, u64>) {
     let mut ore = 0;
+    let mut counts = HashMap::new();
     let mut spare_chemicals = HashMap::new();
     let mut requirements = Vec::new();
 
@@ -56,6 +70,7 @@ fn calc_ore_for_fuel(fuel: u64, reactions: &ReactionMap) -> u64 {
             let output_amount = reaction.output.1;
             let reaction_count = (adj_req_amount - 1) / output_amount + 1;
             let spare = output_amount * reaction_count - adj_req_amount;
+            *counts.entry(req_chem.clone()).or_insert(0) += reaction_count;
 
             // Update the spare count for this ingredient.
             if let Some(existing_spare) = spare_chemicals.get_mut(&req_chem) {
@@ -79,41 +94,93 @@ fn calc_ore_for_fuel(fuel: u64, reactions: &ReactionMap) -> u64 {
         }
     }
 
-    ore
+    (ore, counts)
 }
 
 fn calc_fuel_for_ore(ore: u64, reactions: &ReactionMap) -> u64 {
-    let mut lower = 1;
-    let mut current;
-    let mut upper = 1;
+    let can_produce = |fuel| calc_ore_for_fuel(fuel, reactions) <= ore;
 
-    // Find an upper bound to use for binary search.
-    loop {
-        let used_ore = calc_ore_for_fuel(upper, reactions);
-        if used_ore < ore {
-            upper *= 2;
-        } else {
-            break;
-        }
+    // Even a single fuel may cost more ore than we have.
+    if !can_produce(1) {
+        return 0;
     }
 
-    // Binary search to find the highest amount of fuel we can
-    // produce without using all the ore.
-    loop {
-        current = (upper - lower) / 2 + lower;
-
-        let used_ore = calc_ore_for_fuel(current, reactions);
+    // Find an upper bound to use for binary search.
+    let mut lower = 1;
+    let mut upper = 2;
+    while can_produce(upper) {
+        lower = upper;
+        upper *= 2;
+    }
 
-        if used_ore < ore {
+    // Binary search to find the highest amount of fuel we can produce,
+    // keeping lower as an amount we can produce, and upper as one we can't.
+    while upper - lower > 1 {
+        let current = (upper - lower) / 2 + lower;
+        if can_produce(current) {
             lower = current;
         } else {
             upper = current;
         }
+    }
+
+    lower
+}
+
+// Find all the reactions that take ORE as an ingredient, sorted by the name
+// of the chemical they produce.
+fn ore_consumers(reactions: &ReactionMap) -> Vec<&Reaction> {
+    let mut consumers: Vec<&Reaction> = reactions
+        .values()
+        .filter(|r| r.ingredients.iter().any(|(chem, _)| chem == "ORE"))
+        .collect();
+    consumers.sort_by(|a, b| a.output.0.cmp(&b.output.0));
+    consumers
+}
 
-        if upper - 1 == lower {
-            return lower;
+// Check that every ingredient other than ORE can be produced, and that no
+// chemical requires itself, which would make the reactions impossible to
+// resolve.
+fn validate_reactions(reactions: &ReactionMap) -> Result<(), String> {
+    let mut on_path = HashSet::new();
+    let mut done = HashSet::new();
+
+    let mut chemicals: Vec<&String> = reactions.keys().collect();
+    chemicals.sort();
+    for chemical in chemicals {
+        visit_reaction(chemical, reactions, &mut on_path, &mut done)?;
+    }
+
+    Ok(())
+}
+
+// Depth-first search through the ingredients of a chemical, tracking the
+// chemicals on the current path so that any cycle is found.
+fn visit_reaction<'a>(
+    chemical: &'a str,
+    reactions: &'a ReactionMap,
+    on_path: &mut HashSet<&'a str>,
+    done: &mut HashSet<&'a str>,
+) -> Result<(), String> {
+    if done.contains(chemical) {
+        return Ok(());
+    }
+    if !on_path.insert(chemical) {
+        return Err(format!("Reactions for {} form a cycle", chemical));
+    }
+
+    let reaction = reactions
+        .get(chemical)
+        .ok_or_else(|| format!("No reaction produces {}", chemical))?;
+    for (ingredient, _) in &reaction.ingredients {
+        if ingredient != "ORE" {
+            visit_reaction(ingredient, reactions, on_path, done)?;
         }
     }
+
+    on_path.remove(chemical);
+    done.insert(chemical);
+    Ok(())
 }
 
 fn parse_chemical(chemical: &str) -> (String, u64) {
@@ -150,7 +217,7 @@ fn parse_reactions(strs: &[String]) -> ReactionMap {
     reactions
 }
 
-fn parse_input(filename: &str) -> ReactionMap {
+fn parse_input(filename: &str) -> Result<ReactionMap, String> {
     let file = File::open(filename).expect("Failed to open file");
     let reader = BufReader::new(file);
 
@@ -159,16 +226,39 @@ fn parse_input(filename: &str) -> ReactionMap {
         .map(|l| l.expect("Failed to read line"))
         .map(|l| String::from(l.trim()))
         .collect();
-    parse_reactions(reactions.as_slice())
+    let reactions = parse_reactions(reactions.as_slice());
+    validate_reactions(&reactions)?;
+    Ok(reactions)
 }
 
 fn main() {
-    let reactions = parse_input("input");
+    let reactions = match parse_input("input") {
+        Ok(reactions) => reactions,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
 
     // Part 1
     let ore = calc_ore(&reactions);
     println!("Require {} ore for 1 fuel", ore);
 
+    let base_chemicals: Vec<&str> = ore_consumers(&reactions)
+        .iter()
+        .map(|r| r.output.0.as_ref())
+        .collect();
+    println!("Chemicals made directly from ore: {:?}", base_chemicals);
+
+    if env::args().any(|a| a == "--counts") {
+        let counts = reaction_counts(1, &reactions);
+        let mut chemicals: Vec<&String> = counts.keys().collect();
+        chemicals.sort();
+        for chemical in chemicals {
+            println!("{}: {} reactions", chemical, counts[chemical]);
+        }
+    }
+
     // Part 2
     let fuel = calc_fuel_for_ore(COLLECTED_ORE, &reactions);
     println!("Produce {} fuel from {} ore", fuel, COLLECTED_ORE);
@@ -196,6 +286,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn validate() {
+        let input = vec![
+            String::from("1 ORE => 1 A"),
+            String::from("1 A, 1 Y => 1 X"),
+            String::from("1 X => 1 Y"),
+            String::from("1 X => 1 FUEL"),
+        ];
+        let reactions = parse_reactions(input.as_slice());
+        assert_eq!(
+            validate_reactions(&reactions),
+            Err(String::from("Reactions for X form a cycle"))
+        );
+
+        let input = vec![
+            String::from("1 ORE => 1 A"),
+            String::from("1 A, 1 B => 1 FUEL"),
+        ];
+        let reactions = parse_reactions(input.as_slice());
+        assert_eq!(
+            validate_reactions(&reactions),
+            Err(String::from("No reaction produces B"))
+        );
+
+        let input = vec![String::from("1 ORE => 1 A"), String::from("2 A => 1 FUEL")];
+        let reactions = parse_reactions(input.as_slice());
+        assert_eq!(validate_reactions(&reactions), Ok(()));
+    }
+
+    #[test]
+    fn fuel_over_budget() {
+        let input = vec![String::from("10 ORE => 1 FUEL")];
+        let reactions = parse_reactions(input.as_slice());
+        assert_eq!(calc_fuel_for_ore(9, &reactions), 0);
+        assert_eq!(calc_fuel_for_ore(10, &reactions), 1);
+        assert_eq!(calc_fuel_for_ore(55, &reactions), 5);
+    }
+
     #[test]
     fn example1() {
         let input = vec![
@@ -212,6 +340,34 @@ mod tests {
         assert_eq!(result, 31);
     }
 
+    #[test]
+    fn example1_reaction_counts() {
+        let input = vec![
+            String::from("10 ORE => 10 A"),
+            String::from("1 ORE => 1 B"),
+            String::from("7 A, 1 B => 1 C"),
+            String::from("7 A, 1 C => 1 D"),
+            String::from("7 A, 1 D => 1 E"),
+            String::from("7 A, 1 E => 1 FUEL"),
+        ];
+
+        let reactions = parse_reactions(input.as_slice());
+        let counts = reaction_counts(1, &reactions);
+        let mut counts: Vec<(&str, u64)> = counts.iter().map(|(c, n)| (c.as_ref(), *n)).collect();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![
+                ("A", 3),
+                ("B", 1),
+                ("C", 1),
+                ("D", 1),
+                ("E", 1),
+                ("FUEL", 1)
+            ]
+        );
+    }
+
     #[test]
     fn example2() {
         let input = vec![
@@ -253,6 +409,28 @@ mod tests {
         assert_eq!(result, 82892753);
     }
 
+    #[test]
+    fn example3_ore_consumers() {
+        let input = vec![
+            String::from("157 ORE => 5 NZVS"),
+            String::from("165 ORE => 6 DCFZ"),
+            String::from("44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL"),
+            String::from("12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ"),
+            String::from("179 ORE => 7 PSHF"),
+            String::from("177 ORE => 5 HKGWZ"),
+            String::from("7 DCFZ, 7 PSHF => 2 XJWVT"),
+            String::from("165 ORE => 2 GPVTF"),
+            String::from("3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT"),
+        ];
+
+        let reactions = parse_reactions(input.as_slice());
+        let consumers: Vec<&str> = ore_consumers(&reactions)
+            .iter()
+            .map(|r| r.output.0.as_ref())
+            .collect();
+        assert_eq!(consumers, vec!["DCFZ", "GPVTF", "HKGWZ", "NZVS", "PSHF"]);
+    }
+
     #[test]
     fn example4() {
         let input = vec![
@@ -4,6 +4,9 @@ use pathfinding::prelude::{absdiff, astar};
 use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::path::Path;
 
 #[derive(Copy, Clone, Debug, Eq, IntoPrimitive, PartialEq, TryFromPrimitive)]
 #[repr(i64)]
@@ -72,6 +75,13 @@ fn find_path(start: Loc, goal: Loc, map: &Map) -> Vec<Loc> {
         .unwrap();
 }
 
+// Find the route from the start to the oxygen, returning the number of steps
+// and the locations visited along the way, including the start.
+fn route_to_oxygen(map: &Map, oxygen: Loc) -> (usize, Vec<Loc>) {
+    let path = find_path((0, 0), oxygen, map);
+    (path.len() - 1, path)
+}
+
 // Attempt to step the robot in a given direction and return
 // the resulting location type.
 fn step_one(dir: Direction, robot: &mut Program) -> LocType {
@@ -200,15 +210,211 @@ fn fill_oxygen(start: Loc, map: &mut Map) -> u64 {
     minutes
 }
 
+// Characterize the explored maze, returning the number of dead-end tiles
+// (one open neighbour), corridor tiles (two open neighbours) and junction
+// tiles (three or more open neighbours).
+fn maze_stats(map: &Map) -> (usize, usize, usize) {
+    let is_open = |loc: &Loc| matches!(map.get(loc), Some(LocType::Empty) | Some(LocType::Oxygen));
+
+    let mut dead_ends = 0;
+    let mut corridors = 0;
+    let mut junctions = 0;
+    for loc in map.keys().filter(|loc| is_open(loc)) {
+        let open_neighbours = get_neighbour_coords(*loc)
+            .iter()
+            .filter(|n| is_open(n))
+            .count();
+        match open_neighbours {
+            0 => (),
+            1 => dead_ends += 1,
+            2 => corridors += 1,
+            _ => junctions += 1,
+        }
+    }
+
+    (dead_ends, corridors, junctions)
+}
+
+// Draw the explored map, with north at the top. Walls aren't recorded while
+// exploring, so any unexplored tile inside the bounds is drawn as a wall.
+fn render_map(map: &Map, oxygen: Loc) -> String {
+    let min_x = map.keys().map(|loc| loc.0).min().unwrap_or(0) - 1;
+    let max_x = map.keys().map(|loc| loc.0).max().unwrap_or(0) + 1;
+    let min_y = map.keys().map(|loc| loc.1).min().unwrap_or(0) - 1;
+    let max_y = map.keys().map(|loc| loc.1).max().unwrap_or(0) + 1;
+
+    (min_y..=max_y)
+        .rev()
+        .map(|y| {
+            (min_x..=max_x)
+                .map(|x| {
+                    if (x, y) == (0, 0) {
+                        'S'
+                    } else if (x, y) == oxygen {
+                        'O'
+                    } else {
+                        match map.get(&(x, y)) {
+                            Some(LocType::Empty) => '.',
+                            Some(LocType::Oxygen) => 'O',
+                            Some(LocType::Wall) | None => '#',
+                        }
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Save the explored map using the same format as render_map.
+fn save_map(map: &Map, oxygen: Loc, path: &str) {
+    fs::write(path, render_map(map, oxygen) + "\n").expect("Failed to write map file");
+}
+
+// Load a map saved by save_map, returning the map and the location of the
+// oxygen.
+fn load_map(path: &str) -> (Map, Loc) {
+    let contents = fs::read_to_string(path).expect("Failed to read map file");
+    let rows: Vec<&str> = contents.lines().collect();
+
+    // Locations are relative to the start, and north is at the top.
+    let (start_row, start_col) = rows
+        .iter()
+        .enumerate()
+        .find_map(|(row, line)| line.find('S').map(|col| (row, col)))
+        .expect("No start location in map file");
+    let to_loc =
+        |row: usize, col: usize| (col as i64 - start_col as i64, start_row as i64 - row as i64);
+
+    let mut map = HashMap::new();
+    let mut oxygen = None;
+    for (row, line) in rows.iter().enumerate() {
+        for (col, c) in line.chars().enumerate() {
+            let loc = to_loc(row, col);
+            match c {
+                '.' | 'S' => {
+                    map.insert(loc, LocType::Empty);
+                }
+                'O' => {
+                    map.insert(loc, LocType::Oxygen);
+                    oxygen = Some(loc);
+                }
+                '#' => (),
+                _ => panic!("Unexpected character {} in map file", c),
+            }
+        }
+    }
+
+    (map, oxygen.expect("No oxygen in map file"))
+}
+
 fn main() {
-    let mut robot = Program::from_file("input");
-    let (map, oxygen) = explore(&mut robot);
+    // Exploring the map is slow, so it can be cached in a file.
+    let map_file = env::args().skip_while(|a| a != "--map").nth(1);
+    let (map, oxygen) = match map_file {
+        Some(ref path) if Path::new(path).exists() => load_map(path),
+        _ => {
+            let mut robot = Program::from_file("input");
+            let (map, oxygen) = explore(&mut robot);
+            if let Some(path) = map_file {
+                save_map(&map, oxygen, &path);
+            }
+            (map, oxygen)
+        }
+    };
+    println!("{}", render_map(&map, oxygen));
+
+    let (dead_ends, corridors, junctions) = maze_stats(&map);
+    println!(
+        "Maze has {} dead ends, {} corridor tiles and {} junctions",
+        dead_ends, corridors, junctions
+    );
 
     // Part 1
-    let path = find_path((0, 0), oxygen, &map);
-    println!("Robot needs {} steps to get to the oxygen", path.len() - 1);
+    let (steps, path) = route_to_oxygen(&map, oxygen);
+    println!("Robot needs {} steps to get to the oxygen", steps);
+    if env::args().any(|a| a == "--route") {
+        println!("Route: {:?}", path);
+    }
 
     // Part 2
     let minutes = fill_oxygen(oxygen, &mut map.clone());
     println!("Area fills with oxygen in {} minutes", minutes);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats() {
+        // A T-junction with one longer arm, next to a wall.
+        let map: Map = vec![
+            ((0, 0), LocType::Empty),
+            ((1, 0), LocType::Empty),
+            ((2, 0), LocType::Empty),
+            ((3, 0), LocType::Wall),
+            ((1, 1), LocType::Empty),
+            ((1, 2), LocType::Oxygen),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(maze_stats(&map), (3, 1, 1));
+    }
+
+    #[test]
+    fn route() {
+        // A dead-end branch off the route to the oxygen.
+        let map: Map = vec![
+            ((0, 0), LocType::Empty),
+            ((0, 1), LocType::Empty),
+            ((1, 1), LocType::Empty),
+            ((-1, 1), LocType::Empty),
+            ((1, 2), LocType::Empty),
+            ((2, 2), LocType::Oxygen),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            route_to_oxygen(&map, (2, 2)),
+            (4, vec![(0, 0), (0, 1), (1, 1), (1, 2), (2, 2)])
+        );
+    }
+
+    #[test]
+    fn render() {
+        let map: Map = vec![
+            ((0, 0), LocType::Empty),
+            ((1, 0), LocType::Empty),
+            ((1, 1), LocType::Oxygen),
+            ((-1, 0), LocType::Wall),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(render_map(&map, (1, 1)), "#####\n###O#\n##S.#\n#####");
+    }
+
+    #[test]
+    fn save_load() {
+        let map: Map = vec![
+            ((0, 0), LocType::Empty),
+            ((1, 0), LocType::Empty),
+            ((1, 1), LocType::Oxygen),
+            ((1, -1), LocType::Empty),
+            ((0, -1), LocType::Empty),
+        ]
+        .into_iter()
+        .collect();
+
+        let path = std::env::temp_dir().join("day15_save_load");
+        let filename = path.to_str().unwrap();
+        save_map(&map, (1, 1), filename);
+        let loaded = load_map(filename);
+        let _ = fs::remove_file(filename);
+
+        assert_eq!(loaded, (map, (1, 1)));
+    }
+}
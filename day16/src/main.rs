@@ -1,11 +1,40 @@
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
 const OFFSET_LEN: usize = 7;
 const INPUT_REPEAT: usize = 10000;
 
-fn calc_phases(input: &Vec<u8>, phases: u32) -> Vec<u8> {
-    let mut buf = input.clone();
+// Apply the full FFT, where each output digit is the last digit of the sum of
+// the input digits multiplied by the base pattern 0, 1, 0, -1, with each
+// pattern value repeated once per output position.
+fn calc_phases_full(input: &[u8], phases: u32) -> Vec<u8> {
+    const BASE_PATTERN: [i64; 4] = [0, 1, 0, -1];
+
+    let mut buf = input.to_vec();
+    for _ in 0..phases {
+        buf = (0..buf.len())
+            .map(|i| {
+                // The pattern is offset by one, skipping the very first value.
+                let sum: i64 = buf
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &val)| val as i64 * BASE_PATTERN[(j + 1) / (i + 1) % 4])
+                    .sum();
+                (sum.abs() % 10) as u8
+            })
+            .collect();
+    }
+
+    buf
+}
+
+// Apply the FFT using the shortcut that's only valid for the second half of
+// the signal, where the pattern for each output digit is 0 for all the input
+// digits before it and 1 for the rest, so each output digit is the sum of the
+// digits after it.
+fn calc_phases(input: &[u8], phases: u32) -> Vec<u8> {
+    let mut buf = input.to_vec();
 
     for _ in 0..phases {
         let mut sum = 0;
@@ -18,7 +47,7 @@ fn calc_phases(input: &Vec<u8>, phases: u32) -> Vec<u8> {
     buf
 }
 
-fn extract_num(buf: &Vec<u8>, offset: usize, len: usize) -> u64 {
+fn extract_num(buf: &[u8], offset: usize, len: usize) -> u64 {
     let mut result = 0;
     for val in &buf[offset..(offset + len)] {
         result *= 10;
@@ -28,6 +57,58 @@ fn extract_num(buf: &Vec<u8>, offset: usize, len: usize) -> u64 {
     result
 }
 
+// Run the full FFT on the input, and read the first `digits` digits of the
+// output.
+fn solve_part1(input: &[u8], phases: u32, digits: usize) -> Result<u64, String> {
+    if digits > input.len() {
+        return Err(format!(
+            "Can't read {} digits from a signal of length {}",
+            digits,
+            input.len()
+        ));
+    }
+
+    let output = calc_phases_full(input, phases);
+    Ok(extract_num(&output, 0, digits))
+}
+
+// Find the message in the real signal, which is the input repeated
+// INPUT_REPEAT times, at the offset given by the first digits of the input.
+// Only the digits after the offset are calculated, which relies on the offset
+// being in the second half of the signal, where each output digit is just the
+// sum of the digits after it.
+fn solve_part2(input: &[u8]) -> Result<u64, String> {
+    if input.len() < OFFSET_LEN {
+        return Err(format!(
+            "Can't read the message offset from a signal of length {}",
+            input.len()
+        ));
+    }
+
+    let offset = extract_num(input, 0, OFFSET_LEN) as usize;
+    let signal_len = INPUT_REPEAT * input.len();
+    if offset + 8 > signal_len {
+        return Err(format!(
+            "Message offset {} is past the end of the signal",
+            offset
+        ));
+    }
+    if offset < signal_len / 2 {
+        return Err(format!(
+            "Message offset {} is in the first half of the signal",
+            offset
+        ));
+    }
+
+    let input_len = signal_len - offset;
+    let mut repeated_input = Vec::with_capacity(input_len);
+    for i in 0..input_len {
+        repeated_input.push(input[(i + offset) % input.len()]);
+    }
+    let output = calc_phases(&repeated_input, 100);
+    Ok(extract_num(&output, 0, 8))
+}
+
 fn split_input(line: &str) -> Vec<u8> {
     return line
         .trim()
@@ -44,24 +125,30 @@ fn read_input(filename: &str) -> Vec<u8> {
     return split_input(line.as_ref());
 }
 
+// Get the value following a command-line flag, if the flag was given.
+fn arg_value(flag: &str) -> Option<String> {
+    env::args().skip_while(|a| a != flag).nth(1)
+}
+
 fn main() {
+    let filename = arg_value("--input").unwrap_or_else(|| String::from("input"));
+    let phases =
+        arg_value("--phases").map_or(100, |p| p.parse::<u32>().expect("Invalid phase count"));
+    let digits =
+        arg_value("--digits").map_or(8, |d| d.parse::<usize>().expect("Invalid digit count"));
+
     // Part 1
-    let input = read_input("input");
-    let output = calc_phases(&input, 100);
-    let result = extract_num(&output, 0, 8);
-    println!("Part 1 Result: {}", result);
+    let input = read_input(&filename);
+    match solve_part1(&input, phases, digits) {
+        Ok(result) => println!("Part 1 Result: {}", result),
+        Err(e) => println!("Part 1 Error: {}", e),
+    }
 
     // Part 2
-    let offset = extract_num(&input, 0, OFFSET_LEN) as usize;
-
-    let input_len = (INPUT_REPEAT * input.len()) - offset;
-    let mut repeated_input = Vec::with_capacity(input_len);
-    for i in 0..input_len {
-        repeated_input.push(input[(i + offset) % input.len()]);
+    match solve_part2(&input) {
+        Ok(result) => println!("Part 2 Result: {}", result),
+        Err(e) => println!("Part 2 Error: {}", e),
     }
-    let output = calc_phases(&repeated_input, 100);
-    let result = extract_num(&output, 0, 8);
-    println!("Part 2 Result: {}", result);
 }
 
 #[cfg(test)]
@@ -70,7 +157,7 @@ mod tests {
 
     #[test]
     fn pt1_ex1() {
-        let output = calc_phases(&vec![1, 2, 3, 4, 5, 6, 7, 8], 4);
+        let output = calc_phases_full(&[1, 2, 3, 4, 5, 6, 7, 8], 4);
         let result = extract_num(&output, 0, 8);
         assert_eq!(result, 01029498);
     }
@@ -78,7 +165,7 @@ mod tests {
     #[test]
     fn pt1_ex2() {
         let input = split_input("80871224585914546619083218645595");
-        let output = calc_phases(&input, 100);
+        let output = calc_phases_full(&input, 100);
         let result = extract_num(&output, 0, 8);
         assert_eq!(result, 24176176)
     }
@@ -86,7 +173,7 @@ mod tests {
     #[test]
     fn pt1_ex3() {
         let input = split_input("19617804207202209144916044189917");
-        let output = calc_phases(&input, 100);
+        let output = calc_phases_full(&input, 100);
         let result = extract_num(&output, 0, 8);
         assert_eq!(result, 73745418);
     }
@@ -94,16 +181,70 @@ mod tests {
     #[test]
     fn pt1_ex4() {
         let input = split_input("69317163492948606335995924319873");
-        let output = calc_phases(&input, 100);
+        let output = calc_phases_full(&input, 100);
         let result = extract_num(&output, 0, 8);
         assert_eq!(result, 52432133);
     }
 
-    //#[test]
-    fn pt2_e1() {
-        /*let input = split_input("03036732577212944063491565474664");
-        let offset = get_offset(&input);
-        let result = calc_phases(&input, 100, input.len() * INPUT_REPEAT, offset, 8);
-        assert_eq!(result, 84462026);*/
+    #[test]
+    fn full_matches_shortcut() {
+        let input = split_input("80871224585914546619083218645595");
+        let half = input.len() / 2;
+
+        let full = calc_phases_full(&input, 100);
+        let shortcut = calc_phases(&input[half..], 100);
+        assert_eq!(full[half..].to_vec(), shortcut);
+    }
+
+    #[test]
+    fn pt2_ex1() {
+        let input = split_input("03036732577212944063491565474664");
+        assert_eq!(solve_part2(&input), Ok(84462026));
+    }
+
+    #[test]
+    fn pt2_ex2() {
+        let input = split_input("02935109699940807407585447034323");
+        assert_eq!(solve_part2(&input), Ok(78725270));
+    }
+
+    #[test]
+    fn pt2_ex3() {
+        let input = split_input("03081770884921959731165446850517");
+        assert_eq!(solve_part2(&input), Ok(53553731));
+    }
+
+    #[test]
+    fn pt2_first_half() {
+        let input = split_input("00000019617804207202209144916044");
+        assert_eq!(
+            solve_part2(&input),
+            Err(String::from(
+                "Message offset 1 is in the first half of the signal"
+            ))
+        );
+    }
+
+    #[test]
+    fn pt2_short_input() {
+        let input = split_input("12345");
+        assert_eq!(
+            solve_part2(&input),
+            Err(String::from(
+                "Can't read the message offset from a signal of length 5"
+            ))
+        );
+    }
+
+    #[test]
+    fn pt1_too_many_digits() {
+        let input = split_input("12345678");
+        assert_eq!(solve_part1(&input, 4, 8), Ok(1029498));
+        assert_eq!(
+            solve_part1(&input, 4, 9),
+            Err(String::from(
+                "Can't read 9 digits from a signal of length 8"
+            ))
+        );
     }
 }
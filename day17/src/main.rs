@@ -1,4 +1,6 @@
 use intcode::Program;
+use std::collections::HashSet;
+use std::env;
 use std::ops::Range;
 
 const MAX_BUF_LEN: usize = 20;
@@ -82,24 +84,16 @@ type Map = Vec<Vec<TileType>>;
 type Coords = (usize, usize);
 
 fn get_map(program: &Program) -> Map {
-    let mut map = Vec::new();
-    let mut row = Vec::new();
-    program.execute_ex(
-        || 0,
-        |val| {
-            // Hit a newline, start a new row
-            if val == 10 {
-                if !row.is_empty() {
-                    map.push(row.clone());
-                    row.clear();
-                }
-            } else {
-                row.push(TileType::from_ascii(val));
-            }
-        },
-    );
-
-    map
+    program
+        .run_collect_ascii("")
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.bytes()
+                .map(|b| TileType::from_ascii(b as i64))
+                .collect()
+        })
+        .collect()
 }
 
 fn get_neighbour_coords(map: &Map, coords: Coords) -> Vec<Coords> {
@@ -269,6 +263,75 @@ fn gen_path(map: &Map, start: Coords) -> Vec<Command> {
     commands
 }
 
+// Walk the given commands from the start, and check that they stay on the
+// scaffold and visit every scaffold tile. Prints any tiles that aren't
+// visited.
+fn verify_path(map: &Map, start: Coords, commands: &[Command]) -> bool {
+    let mut dir = match map[start.1][start.0] {
+        TileType::Robot(dir) => dir,
+        _ => panic!("Robot isn't at start coords"),
+    };
+    let mut coords = start;
+    let mut visited = HashSet::new();
+    visited.insert(coords);
+
+    for &cmd in commands {
+        match cmd {
+            Command::TurnLeft => {
+                dir = match dir {
+                    Direction::Up => Direction::Left,
+                    Direction::Left => Direction::Down,
+                    Direction::Down => Direction::Right,
+                    Direction::Right => Direction::Up,
+                }
+            }
+            Command::TurnRight => {
+                dir = match dir {
+                    Direction::Up => Direction::Right,
+                    Direction::Right => Direction::Down,
+                    Direction::Down => Direction::Left,
+                    Direction::Left => Direction::Up,
+                }
+            }
+            Command::Move(distance) => {
+                for _ in 0..distance {
+                    let next = match dir {
+                        Direction::Up if coords.1 > 0 => (coords.0, coords.1 - 1),
+                        Direction::Down if coords.1 < map.len() - 1 => (coords.0, coords.1 + 1),
+                        Direction::Left if coords.0 > 0 => (coords.0 - 1, coords.1),
+                        Direction::Right if coords.0 < map[0].len() - 1 => (coords.0 + 1, coords.1),
+                        _ => {
+                            println!("Path leaves the map at {:?}", coords);
+                            return false;
+                        }
+                    };
+                    if !TileType::is_scaffold(map[next.1][next.0]) {
+                        println!("Path leaves the scaffold at {:?}", next);
+                        return false;
+                    }
+
+                    coords = next;
+                    visited.insert(coords);
+                }
+            }
+        }
+    }
+
+    let mut uncovered = Vec::new();
+    for (y, row) in map.iter().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
+            if TileType::is_scaffold(tile) && !visited.contains(&(x, y)) {
+                uncovered.push((x, y));
+            }
+        }
+    }
+
+    if !uncovered.is_empty() {
+        println!("Path doesn't visit scaffold tiles {:?}", uncovered);
+    }
+    uncovered.is_empty()
+}
+
 // Calculate the length of a function.
 fn function_len(commands: &[Command]) -> usize {
     let len: usize = commands
@@ -288,8 +351,7 @@ fn skip_functions(commands: &Vec<Command>, mut start: usize, functions: Vec<&[Co
     loop {
         let mut found = false;
         for f in &functions {
-            let slice = &commands[start..(start + f.len())];
-            if *slice == **f {
+            if commands[start..].starts_with(f) {
                 found = true;
                 start += f.len();
                 break;
@@ -310,8 +372,7 @@ fn check_functions(commands: &Vec<Command>, functions: &Vec<&[Command]>) -> Opti
     loop {
         let mut found = false;
         for (i, f) in functions.iter().enumerate() {
-            let slice = &commands[start..(start + f.len())];
-            if slice == *f {
+            if commands[start..].starts_with(f) {
                 found = true;
                 start += f.len();
                 routine.push(i);
@@ -328,83 +389,79 @@ fn check_functions(commands: &Vec<Command>, functions: &Vec<&[Command]>) -> Opti
         if start == commands.len() {
             return Some(routine);
         }
-
-        // Went too far, not a valid set.
-        if start > commands.len() {
-            return None;
-        }
     }
 }
 
+// Get the commands in the given range as a candidate function, if they fit in
+// max_len characters.
 fn get_candidate_function<'a>(
     commands: &'a Vec<Command>,
     range: &Range<usize>,
+    max_len: usize,
 ) -> Option<&'a [Command]> {
-    if range.end >= commands.len() - 1 {
+    if range.end > commands.len() {
         return None;
     }
     let function = &commands[range.clone()];
-    if function_len(function) > MAX_BUF_LEN {
+    if function_len(function) > max_len {
         return None;
     }
 
     Some(function)
 }
 
-fn find_movement_routine(commands: &Vec<Command>) -> (Vec<usize>, Vec<&[Command]>) {
-    let mut a_range = 0..1;
-
-    loop {
-        // Loop over possible A functions
-        let candidate = get_candidate_function(commands, &a_range);
-        if candidate.is_none() {
-            break;
-        }
-        let a_function = candidate.unwrap();
-        let b_start = skip_functions(commands, a_range.end, vec![a_function]);
-        let mut b_range = b_start..(b_start + 1);
-
-        loop {
-            // Loop over possible B functions, given the current A function
-            let candidate = get_candidate_function(commands, &b_range);
-            if candidate.is_none() {
-                break;
-            }
-            let b_function = candidate.unwrap();
-            let c_start = skip_functions(commands, b_range.end, vec![a_function, b_function]);
-            let mut c_range = c_start..(c_start + 1);
-
-            loop {
-                // Loop over possible C functions, given the current A and B functions
-                let candidate = get_candidate_function(commands, &c_range);
-                if candidate.is_none() {
-                    break;
-                }
-                let c_function = candidate.unwrap();
-
-                let fns = vec![a_function, b_function, c_function];
+fn find_movement_routine(commands: &Vec<Command>, max_len: usize) -> (Vec<usize>, Vec<&[Command]>) {
+    find_movement_routine_n(commands, 3, max_len).expect("Failed to find possible functions")
+}
 
-                if let Some(fn_indices) = check_functions(commands, &fns) {
-                    return (fn_indices, fns);
-                }
+// Find a movement routine that uses n functions, each at most max_len
+// characters long, to execute the given commands.
+fn find_movement_routine_n(
+    commands: &Vec<Command>,
+    n: usize,
+    max_len: usize,
+) -> Option<(Vec<usize>, Vec<&[Command]>)> {
+    let mut functions = Vec::new();
+    let routine = search_functions(commands, &mut functions, n, max_len, 0)?;
+    Some((routine, functions))
+}
 
-                c_range.end += 1;
-            }
+// Loop over the possible next functions, given the functions chosen so far,
+// recursing until there are n functions and checking whether they execute
+// the required commands. Each new function starts at the first command, after
+// the end of the previous function, that isn't covered by the functions so
+// far.
+fn search_functions<'a>(
+    commands: &'a Vec<Command>,
+    functions: &mut Vec<&'a [Command]>,
+    n: usize,
+    max_len: usize,
+    prev_end: usize,
+) -> Option<Vec<usize>> {
+    if functions.len() == n {
+        return check_functions(commands, functions);
+    }
 
-            b_range.end += 1;
+    let start = skip_functions(commands, prev_end, functions.clone());
+    let mut range = start..(start + 1);
+    while let Some(function) = get_candidate_function(commands, &range, max_len) {
+        functions.push(function);
+        if let Some(routine) = search_functions(commands, functions, n, max_len, range.end) {
+            return Some(routine);
         }
+        functions.pop();
 
-        a_range.end += 1;
+        range.end += 1;
     }
 
-    panic!("Failed to find possible functions");
+    None
 }
 
 // Convert the movement routine, in the form of a vector of functions, and a vector
 // of indices into the function vector representing the order in which to execute
 // those functions, into a vector of ascii chars to provide as input to the robot
-// program.
-fn make_robot_input(routine: &Vec<usize>, functions: &Vec<&[Command]>) -> Vec<u8> {
+// program, along with whether to enable the robot's video feed.
+fn make_robot_input(routine: &Vec<usize>, functions: &Vec<&[Command]>, video: bool) -> Vec<u8> {
     let routine_str = routine
         .iter()
         .map(|&i| match i {
@@ -426,8 +483,7 @@ fn make_robot_input(routine: &Vec<usize>, functions: &Vec<&[Command]>) -> Vec<u8
         })
         .collect::<Vec<String>>();
 
-    // Disable video output.
-    let video_str = String::from("n");
+    let video_str = String::from(if video { "y" } else { "n" });
 
     let mut input_strs: Vec<String> = vec![routine_str];
     input_strs.extend(function_strs);
@@ -440,21 +496,60 @@ fn make_robot_input(routine: &Vec<usize>, functions: &Vec<&[Command]>) -> Vec<u8
     input_strs.join("").chars().map(|c| c as u8).collect()
 }
 
-// Move the vacuum robot by executing the given movement routine input.
-fn move_robot(program: &Program, input: &Vec<u8>) -> i64 {
+// Move the vacuum robot by executing the given movement routine input. If the
+// video feed is enabled, print the frames the robot outputs as it moves.
+fn move_robot(program: &Program, input: &Vec<u8>, video: bool) -> i64 {
     let mut program = program.clone();
     program.poke(0, 2);
 
     let mut input_iter = input.iter();
     let mut output = None;
 
-    program.execute_ex(|| *input_iter.next().unwrap() as i64, |v| output = Some(v));
+    program.execute_ex(
+        || *input_iter.next().unwrap() as i64,
+        |v| {
+            if video && v < 128 {
+                print!("{}", v as u8 as char);
+            }
+            output = Some(v)
+        },
+    );
     output.unwrap()
 }
 
+// Move the vacuum robot using a manually supplied movement routine and
+// functions, e.g. "A,B,A" and "R,8,L,4", bypassing the solver.
+fn move_robot_with_routine(
+    program: &Program,
+    routine: &str,
+    a: &str,
+    b: &str,
+    c: &str,
+    video: bool,
+) -> i64 {
+    let video_str = if video { "y" } else { "n" };
+    let input = format!("{}\n{}\n{}\n{}\n{}\n", routine, a, b, c, video_str);
+    move_robot(program, &input.into_bytes(), video)
+}
+
 fn main() {
     let program = Program::from_file("input");
 
+    let video = env::args().any(|a| a == "--video");
+
+    // A movement routine and functions can be given on the command line,
+    // in case the solver can't find one.
+    let args: Vec<String> = env::args()
+        .skip(1)
+        .filter(|a| !a.starts_with("--"))
+        .collect();
+    if args.len() == 4 {
+        let result =
+            move_robot_with_routine(&program, &args[0], &args[1], &args[2], &args[3], video);
+        println!("Vacuumed {} dust", result);
+        return;
+    }
+
     let map = get_map(&program);
     print_map(&map);
 
@@ -467,10 +562,95 @@ fn main() {
     let vacuum_coords = find_vacuum(&map);
     let commands = gen_path(&map, vacuum_coords);
     println!("Commands: {:?}", commands);
-    let (routine, functions) = find_movement_routine(&commands);
+    assert!(
+        verify_path(&map, vacuum_coords, &commands),
+        "Generated path doesn't cover the scaffold"
+    );
+    let (routine, functions) = find_movement_routine(&commands, MAX_BUF_LEN);
     println!("Routine: {:?}, Functions: {:?}", routine, functions);
-    let program_input = make_robot_input(&routine, &functions);
+    let program_input = make_robot_input(&routine, &functions, video);
     println!("{:?}", program_input);
-    let result = move_robot(&program, &program_input);
+    let result = move_robot(&program, &program_input, video);
     println!("Vacuumed {} dust", result);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_routine() {
+        // A stand-in for the vacuum robot, which reads lines of input until
+        // it has read five, then outputs the number of bytes it read. The
+        // first instruction is overwritten when the robot is woken up.
+        let program = Program::from_str(
+            "1,0,0,100,3,100,1001,103,1,103,1008,100,10,101,1006,101,4,\
+             1001,102,1,102,1008,102,5,101,1006,101,4,4,103,99",
+        );
+
+        let result = move_robot_with_routine(&program, "A,B", "R,8", "L,4", "R,2", false);
+        assert_eq!(result, 18);
+    }
+
+    #[test]
+    fn cross_coverage() {
+        let map: Map = ["..#..", "..#..", "#####", "..#..", "..>.."]
+            .iter()
+            .map(|line| {
+                line.bytes()
+                    .map(|b| TileType::from_ascii(b as i64))
+                    .collect()
+            })
+            .collect();
+        let start = find_vacuum(&map);
+
+        // Only turning at dead ends goes straight through the intersection
+        // and misses the arms.
+        let commands = gen_path(&map, start);
+        assert_eq!(commands, vec![Command::TurnLeft, Command::Move(4)]);
+        assert!(!verify_path(&map, start, &commands));
+
+        // Visit each arm of the cross in turn.
+        let (l, r) = (Command::TurnLeft, Command::TurnRight);
+        let commands = vec![
+            l,
+            Command::Move(2),
+            l,
+            Command::Move(2),
+            l,
+            l,
+            Command::Move(4),
+            l,
+            l,
+            Command::Move(2),
+            r,
+            Command::Move(2),
+        ];
+        assert!(verify_path(&map, start, &commands));
+
+        // Walking off the scaffold is also caught.
+        assert!(!verify_path(&map, start, &[Command::Move(1)]));
+    }
+
+    #[test]
+    fn four_functions() {
+        // Each rotation of the four moves appears once, so no function can
+        // cover more than one move.
+        let (a, b, c, d) = (
+            Command::Move(1000),
+            Command::Move(2000),
+            Command::Move(3000),
+            Command::Move(4000),
+        );
+        let commands = vec![a, b, c, d, b, c, d, a, c, d, a, b, d, a, b, c];
+
+        assert!(find_movement_routine_n(&commands, 3, MAX_BUF_LEN).is_none());
+
+        let (routine, functions) = find_movement_routine_n(&commands, 4, MAX_BUF_LEN).unwrap();
+        assert_eq!(
+            routine,
+            vec![0, 1, 2, 3, 1, 2, 3, 0, 2, 3, 0, 1, 3, 0, 1, 2]
+        );
+        assert_eq!(functions, vec![[a], [b], [c], [d]]);
+    }
+}
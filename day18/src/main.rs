@@ -1,11 +1,19 @@
+use pathfinding::prelude::dijkstra;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::env;
+use std::fs;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io::{BufRead, BufReader};
 
 type Coords = (usize, usize);
 
+// A set of keys, as a bitmask with bit 0 for key 'a', bit 1 for 'b' and so on.
+type KeySet = u32;
+
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
 enum Tile {
     Wall,
@@ -34,10 +42,15 @@ struct Map {
     starts: Vec<Tile>,
     keys: HashMap<char, Coords>,
 
+    // Hash of the input lines, used to check a reachability cache file
+    // was generated from the same map.
+    input_hash: u64,
+
     // Map from a tile (the entrance or a key) to a vector containing
     // the keys that can be reached from that key, the distance for
-    // each key, and any doors that need to be unlocked.
-    reachability: HashMap<Tile, Vec<(char, usize, HashSet<char>)>>,
+    // each key, and a mask of the keys for any doors that need to be
+    // unlocked.
+    reachability: HashMap<Tile, Vec<(char, usize, KeySet)>>,
 }
 
 impl Map {
@@ -45,6 +58,9 @@ impl Map {
         let mut tiles = Vec::new();
         let mut starts = Vec::new();
         let mut keys = HashMap::new();
+        let mut hasher = DefaultHasher::new();
+        lines.hash(&mut hasher);
+
         for (y, line) in lines.iter().enumerate() {
             let mut row = Vec::new();
             for (x, c) in line.chars().enumerate() {
@@ -66,6 +82,7 @@ impl Map {
             tiles: tiles,
             starts: starts,
             keys: keys,
+            input_hash: hasher.finish(),
             reachability: HashMap::new(),
         }
     }
@@ -99,12 +116,12 @@ impl Map {
         self.tiles[coords.1][coords.0]
     }
 
-    fn find_keys_from_coords(self: &Self, coords: Coords) -> Vec<(char, usize, HashSet<char>)> {
-        let mut keys: HashMap<char, (usize, HashSet<char>)> = HashMap::new();
+    fn find_keys_from_coords(self: &Self, coords: Coords) -> Vec<(char, usize, KeySet)> {
+        let mut keys: HashMap<char, (usize, KeySet)> = HashMap::new();
 
         let mut visited: HashSet<Coords> = HashSet::new();
-        let mut queue: VecDeque<(Coords, usize, HashSet<char>)> = VecDeque::new();
-        queue.push_back((coords, 0, HashSet::new()));
+        let mut queue: VecDeque<(Coords, usize, KeySet)> = VecDeque::new();
+        queue.push_back((coords, 0, 0));
 
         while !queue.is_empty() {
             let (coords, d, required_keys) = queue.pop_front().unwrap();
@@ -119,16 +136,14 @@ impl Map {
 
                 match self.get_tile(coords) {
                     Tile::Key(c) => {
-                        keys.insert(c, (distance, required_keys.clone()));
-                        queue.push_back((coords, distance, required_keys.clone()));
+                        keys.insert(c, (distance, required_keys));
+                        queue.push_back((coords, distance, required_keys));
                     }
                     Tile::Door(c) => {
-                        let mut new_required_keys = required_keys.clone();
-                        new_required_keys.insert(c);
-                        queue.push_back((coords, distance, new_required_keys));
+                        queue.push_back((coords, distance, required_keys | key_bit(c)));
                     }
                     Tile::Floor | Tile::Entrance(_) => {
-                        queue.push_back((coords, distance, required_keys.clone()))
+                        queue.push_back((coords, distance, required_keys))
                     }
 
                     _ => (),
@@ -158,81 +173,247 @@ impl Map {
         self.reachability.extend(key_info);
     }
 
-    fn make_memo_key(current_locs: &Vec<Tile>, keys: &HashSet<char>) -> String {
-        // Don't sort the locations - the order is important for the case where more
-        // than one current location is at an entrance
-        let loc_str: String = current_locs
-            .iter()
-            .map(|t| match t {
-                Tile::Entrance(_) => '@',
-                Tile::Key(c) => *c,
-                _ => panic!("Current location neither an entrance nor a key"),
-            })
-            .collect();
-
-        let mut keyvec = Vec::new();
-        for c in keys {
-            keyvec.push(*c);
+    // Write the reachability map to a cache file. The first line is the hash
+    // of the input, then there is one line per reachable key in the form
+    // "<from> <key> <distance> <doors>", where <from> is either a key or
+    // an entrance in the form "@x,y", and <doors> is "-" if there are none.
+    fn save_reachability(&self, filename: &str) {
+        let mut lines = vec![self.input_hash.to_string()];
+        for (tile, reachable) in &self.reachability {
+            let from = match tile {
+                Tile::Entrance((x, y)) => format!("@{},{}", x, y),
+                Tile::Key(c) => c.to_string(),
+                _ => panic!("Reachability from a tile that isn't a key or entrance"),
+            };
+
+            for (key, distance, doors) in reachable {
+                let doors: String = if *doors == 0 {
+                    String::from("-")
+                } else {
+                    ('a'..='z').filter(|&c| doors & key_bit(c) != 0).collect()
+                };
+                lines.push(format!("{} {} {} {}", from, key, distance, doors));
+            }
         }
-        keyvec.sort();
 
-        format!("{}{}", loc_str, keyvec.iter().collect::<String>())
+        fs::write(filename, lines.join("\n") + "\n").expect("Failed to write cache");
     }
 
-    fn find_shortest_path(
-        &self,
-        keys: HashSet<char>,
-        current_tiles: Vec<Tile>,
-        memo: &mut HashMap<String, usize>,
-    ) -> usize {
-        if keys.len() == self.keys.len() {
-            return 0;
+    // Load the reachability map from a cache file. Returns false, leaving the
+    // reachability map untouched, if the cache doesn't exist or was generated
+    // for a different input.
+    fn load_reachability(&mut self, filename: &str) -> bool {
+        let contents = match fs::read_to_string(filename) {
+            Ok(contents) => contents,
+            Err(_) => return false,
+        };
+
+        let mut lines = contents.lines();
+        if lines.next() != Some(self.input_hash.to_string().as_ref()) {
+            return false;
         }
 
-        let mut all_distances = Vec::new();
-        for i in 0..current_tiles.len() {
-            let distances: Vec<usize> = self.reachability[&current_tiles[i]]
-                .iter()
-                .filter(|(c, _, req_keys)| !keys.contains(c) && req_keys.is_subset(&keys))
-                .map(|(c, d, _)| {
-                    let mut new_current_tiles = current_tiles.clone();
-                    new_current_tiles[i] = Tile::Key(*c);
-
-                    let memo_key = Map::make_memo_key(&new_current_tiles, &keys);
-                    if let Some(distance) = memo.get(&memo_key) {
-                        d + *distance
-                    } else {
-                        let mut new_keys = keys.clone();
-                        new_keys.insert(*c);
-
-                        let distance = self.find_shortest_path(new_keys, new_current_tiles, memo);
-                        memo.insert(memo_key, distance);
-                        d + distance
-                    }
-                })
-                .collect();
-
-            if !distances.is_empty() {
-                all_distances.push(*distances.iter().min().unwrap());
+        let mut reachability: HashMap<Tile, Vec<(char, usize, KeySet)>> = HashMap::new();
+        for line in lines {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() != 4 {
+                return false;
             }
+
+            let from = if let Some(coords) = parts[0].strip_prefix('@') {
+                let coords: Vec<usize> = coords.split(',').filter_map(|c| c.parse().ok()).collect();
+                if coords.len() != 2 {
+                    return false;
+                }
+                Tile::Entrance((coords[0], coords[1]))
+            } else {
+                match parts[0].chars().next() {
+                    Some(c) => Tile::Key(c),
+                    None => return false,
+                }
+            };
+
+            let key = match parts[1].chars().next() {
+                Some(c) => c,
+                None => return false,
+            };
+            let distance = match parts[2].parse::<usize>() {
+                Ok(d) => d,
+                Err(_) => return false,
+            };
+            let doors = match parts[3] {
+                "-" => 0,
+                d if d.chars().all(|c| c.is_ascii_lowercase()) => {
+                    d.chars().fold(0, |mask, c| mask | key_bit(c))
+                }
+                _ => return false,
+            };
+
+            reachability
+                .entry(from)
+                .or_default()
+                .push((key, distance, doors));
         }
 
-        if all_distances.is_empty() {
-            return 0;
-        } else {
-            return *all_distances.iter().min().unwrap();
+        self.reachability = reachability;
+        true
+    }
+
+    // Build the reachability map, using the given cache file if it is valid
+    // for this map, and creating it otherwise.
+    fn build_reachability_cached(&mut self, filename: &str) {
+        if !self.load_reachability(filename) {
+            self.build_reachability();
+            self.save_reachability(filename);
         }
     }
+
+    // Find the shortest distance to collect every key, using Dijkstra over
+    // states made up of the current tile for each robot and the set of keys
+    // collected so far.
+    fn find_shortest_path(&self) -> usize {
+        let all_keys = self.keys.keys().fold(0, |mask, &c| mask | key_bit(c));
+
+        let successors = |(tiles, held): &(Vec<Tile>, KeySet)| {
+            let mut next = Vec::new();
+            for (i, tile) in tiles.iter().enumerate() {
+                for &(c, d, doors) in &self.reachability[tile] {
+                    if held & key_bit(c) == 0 && doors & held == doors {
+                        let mut new_tiles = tiles.clone();
+                        new_tiles[i] = Tile::Key(c);
+                        next.push(((new_tiles, held | key_bit(c)), d));
+                    }
+                }
+            }
+            next
+        };
+
+        dijkstra(&(self.starts.clone(), 0), successors, |(_, held)| {
+            *held == all_keys
+        })
+        .map(|(_, distance)| distance)
+        .expect("Couldn't collect all the keys")
+    }
+}
+
+// The bit representing a key in a KeySet.
+fn key_bit(key: char) -> KeySet {
+    1 << (key as u32 - 'a' as u32)
 }
 
 fn main() {
-    let mut map = Map::from_file("input");
-    map.build_reachability();
-    let shortest = map.find_shortest_path(HashSet::new(), map.starts.clone(), &mut HashMap::new());
+    // Building the reachability map is slow for large inputs, optionally
+    // cache it next to the input file.
+    let use_cache = env::args().any(|a| a == "--cache");
+    let load_map = |filename: &str| {
+        let mut map = Map::from_file(filename);
+        if use_cache {
+            map.build_reachability_cached(&format!("{}.cache", filename));
+        } else {
+            map.build_reachability();
+        }
+        map
+    };
+
+    let map = load_map("input");
+    let shortest = map.find_shortest_path();
     println!("Part 1: {}", shortest);
 
-    let mut map = Map::from_file("input2");
-    map.build_reachability();
-    let shortest = map.find_shortest_path(HashSet::new(), map.starts.clone(), &mut HashMap::new());
+    let map = load_map("input2");
+    let shortest = map.find_shortest_path();
     println!("Part 2: {}", shortest);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reachability_cache() {
+        let lines = vec![
+            String::from("########################"),
+            String::from("#f.D.E.e.C.b.A.@.a.B.c.#"),
+            String::from("######################.#"),
+            String::from("#d.....................#"),
+            String::from("########################"),
+        ];
+
+        let mut map = Map::from_lines(&lines);
+        map.build_reachability();
+        let fresh = map.find_shortest_path();
+        assert_eq!(fresh, 86);
+
+        let path = env::temp_dir().join("day18_reachability_cache");
+        let filename = path.to_str().unwrap();
+        map.save_reachability(filename);
+
+        let mut cached_map = Map::from_lines(&lines);
+        assert!(cached_map.load_reachability(filename));
+        let cached = cached_map.find_shortest_path();
+        assert_eq!(cached, fresh);
+
+        // A cache generated from a different map must be rejected.
+        let mut other_map = Map::from_lines(&[String::from("#@a#")]);
+        assert!(!other_map.load_reachability(filename));
+
+        let _ = fs::remove_file(filename);
+    }
+
+    fn shortest_path(lines: &[&str]) -> usize {
+        let lines: Vec<String> = lines.iter().map(|l| String::from(*l)).collect();
+        let mut map = Map::from_lines(&lines);
+        map.build_reachability();
+        map.find_shortest_path()
+    }
+
+    #[test]
+    fn pt2_examples() {
+        assert_eq!(
+            shortest_path(&[
+                "#######", "#a.#Cd#", "##@#@##", "#######", "##@#@##", "#cB#Ab#", "#######",
+            ]),
+            8
+        );
+
+        assert_eq!(
+            shortest_path(&[
+                "###############",
+                "#d.ABC.#.....a#",
+                "######@#@######",
+                "###############",
+                "######@#@######",
+                "#b.....#.....c#",
+                "###############",
+            ]),
+            24
+        );
+
+        assert_eq!(
+            shortest_path(&[
+                "#############",
+                "#DcBa.#.GhKl#",
+                "#.###@#@#I###",
+                "#e#d#####j#k#",
+                "###C#@#@###J#",
+                "#fEbA.#.FgHi#",
+                "#############",
+            ]),
+            32
+        );
+
+        assert_eq!(
+            shortest_path(&[
+                "#############",
+                "#g#f.D#..h#l#",
+                "#F###e#E###.#",
+                "#dCba@#@BcIJ#",
+                "#############",
+                "#nK.L@#@G...#",
+                "#M###N#H###.#",
+                "#o#m..#i#jk.#",
+                "#############",
+            ]),
+            72
+        );
+    }
+}
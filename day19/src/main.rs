@@ -1,9 +1,14 @@
 use intcode::Program;
+use std::env;
 
-const SQUARE_SIZE: i64 = 100;
+const DEFAULT_SQUARE_SIZE: i64 = 100;
+const SCAN_SIZE: i64 = 50;
+
+// How far along a row to look for the beam, as a multiple of the row number.
+const MAX_BEAM_SLOPE: i64 = 10;
 
 enum Result {
-    Big,
+    Big(i64, i64),
     Small,
     Fits(i64, i64),
 }
@@ -26,10 +31,27 @@ fn is_tractor_beam(prg: &Program, x: i64, y: i64) -> bool {
     result
 }
 
-fn find_row_bounds(prg: &Program, y: i64) -> (i64, i64) {
+// Count the points in the size x size region nearest the emitter that are
+// affected by the beam.
+fn count_beam_points(prg: &Program, size: i64) -> u64 {
+    let mut count = 0;
+    for y in 0..size {
+        for x in 0..size {
+            if is_tractor_beam(prg, x, y) {
+                count += 1;
+            }
+        }
+    }
+
+    count
+}
+
+// Find the first and last x coordinates of the beam in a row, giving up
+// if the beam isn't found before max_x.
+fn find_row_bounds(prg: &Program, y: i64, max_x: i64) -> Option<(i64, i64)> {
     let mut bounds = (None, None);
     let mut x = 0;
-    while bounds.1.is_none() {
+    while bounds.1.is_none() && x <= max_x {
         if is_tractor_beam(prg, x, y) {
             if bounds.0.is_none() {
                 bounds.0 = Some(x);
@@ -44,26 +66,32 @@ fn find_row_bounds(prg: &Program, y: i64) -> (i64, i64) {
         x += 1;
     }
 
-    (bounds.0.unwrap(), bounds.1.unwrap())
+    match bounds {
+        (Some(start), Some(end)) => Some((start, end)),
+        _ => None,
+    }
 }
 
-fn square_fits(prg: &Program, y: i64) -> Result {
+fn square_fits(prg: &Program, y: i64, size: i64) -> Result {
     println!("Trying row {}", y);
 
-    let bounds = find_row_bounds(prg, y);
+    let bounds = match find_row_bounds(prg, y, MAX_BEAM_SLOPE * (y + 1)) {
+        Some(bounds) => bounds,
+        None => return Result::Small,
+    };
 
-    if bounds.1 - bounds.0 < (SQUARE_SIZE - 2) {
+    if bounds.1 - bounds.0 < (size - 1) {
         return Result::Small;
     }
 
-    let left = bounds.1 - (SQUARE_SIZE - 1);
-    let bottom = y + (SQUARE_SIZE - 1);
+    let left = bounds.1 - (size - 1);
+    let bottom = y + (size - 1);
 
     let prev_in_beam = is_tractor_beam(prg, left - 1, bottom);
     let cur_in_beam = is_tractor_beam(prg, left, bottom);
 
     if prev_in_beam && cur_in_beam {
-        return Result::Big;
+        return Result::Big(left, y);
     } else if !prev_in_beam && cur_in_beam {
         return Result::Fits(left, y);
     } else {
@@ -71,52 +99,53 @@ fn square_fits(prg: &Program, y: i64) -> Result {
     }
 }
 
-fn main() {
-    let mut prg = Program::from_file("input");
-
-    let mut lower = 10;
-    let mut current = lower;
-    let mut upper = lower;
-    let mut result = None;
+// Find the top-left corner of the closest size x size square that fits
+// entirely in the beam.
+fn find_closest_square(prg: &Program, size: i64) -> (i64, i64) {
+    let mut lower = 0;
+    let mut current = 1;
 
     // Find an upper bound
-    loop {
-        match square_fits(&prg, current) {
-            Result::Small => {
-                lower = current;
-                current *= 2;
-            }
-            Result::Big => {
-                upper = current;
-                break;
-            }
-            Result::Fits(x, y) => {
-                result = Some((x, y));
-                break;
-            }
-        }
+    while let Result::Small = square_fits(prg, current, size) {
+        lower = current;
+        current *= 2;
     }
+    let mut upper = current;
 
     println!("Bounds: ({}, {})", lower, upper);
 
-    // Binary search
-    while result.is_none() {
+    // Binary search until only a couple of rows are left. A square that
+    // fits against the edge of the beam isn't necessarily the closest, as
+    // the beam's edge can step along with the square for a few rows.
+    while upper - lower > 1 {
         current = lower + (upper - lower) / 2;
 
-        match square_fits(&prg, current) {
-            Result::Small => {
-                lower = current;
-            }
-            Result::Big => {
-                upper = current;
-            }
-            Result::Fits(x, y) => {
-                result = Some((x, y));
-            }
+        match square_fits(prg, current, size) {
+            Result::Small => lower = current,
+            Result::Big(_, _) | Result::Fits(_, _) => upper = current,
         }
     }
 
-    let result = result.unwrap();
+    // Settle the remaining rows by taking the first one the square fits.
+    for y in lower..=upper {
+        match square_fits(prg, y, size) {
+            Result::Small => (),
+            Result::Big(x, y) | Result::Fits(x, y) => return (x, y),
+        }
+    }
+
+    panic!("No square found between rows {} and {}", lower, upper);
+}
+
+fn main() {
+    let prg = Program::from_file("input");
+    let size = env::args().nth(1).map_or(DEFAULT_SQUARE_SIZE, |s| {
+        s.parse::<i64>().expect("Invalid square size")
+    });
+
+    println!("Points affected: {}", count_beam_points(&prg, SCAN_SIZE));
+
+    let result = find_closest_square(&prg, size);
     println!(
         "Closest point: ({}, {}). Result: {}",
         result.0,
@@ -124,3 +153,44 @@ fn main() {
         result.0 * 10000 + result.1
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A beam covering y <= x <= 2y on each row, so the closest 10x10
+    // square has its top-right corner at (36, 18).
+    const BEAM_PRG: &str = "3,100,3,101,7,100,101,102,1,101,101,103,7,103,100,104,\
+                            1,102,104,105,1008,105,0,107,4,107,99";
+
+    // A beam covering y < x <= 2y, which leaves the first row empty.
+    const GAP_BEAM_PRG: &str = "3,100,3,101,7,101,100,102,1,101,101,103,7,103,100,104,\
+                                1002,104,-1,104,1,102,104,105,1008,105,1,107,4,107,99";
+
+    #[test]
+    fn empty_row() {
+        let prg = Program::from_str(GAP_BEAM_PRG);
+        assert_eq!(find_row_bounds(&prg, 0, 20), None);
+        assert_eq!(find_row_bounds(&prg, 2, 20), Some((3, 4)));
+        assert!(matches!(square_fits(&prg, 0, 10), Result::Small));
+    }
+
+    #[test]
+    fn beam_points() {
+        let prg = Program::from_str(BEAM_PRG);
+        assert_eq!(count_beam_points(&prg, 5), 9);
+    }
+
+    #[test]
+    fn small_square() {
+        let prg = Program::from_str(BEAM_PRG);
+        assert_eq!(find_closest_square(&prg, 10), (27, 18));
+    }
+
+    #[test]
+    fn tiny_squares() {
+        let prg = Program::from_str(BEAM_PRG);
+        assert_eq!(find_closest_square(&prg, 2), (3, 2));
+        assert_eq!(find_closest_square(&prg, 3), (6, 4));
+    }
+}
@@ -1,71 +1,62 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-const OPCODE_ADD: usize = 1;
-const OPCODE_MUL: usize = 2;
-
-const MIN_INPUT: usize = 0;
-const MAX_INPUT: usize = 99;
-
-const TARGET_OUTPUT: usize = 19690720;
-
-fn get_program(filename: &str) -> Vec<usize> {
-    let file = File::open(filename).expect("Failed to open file");
-    let mut reader = BufReader::new(file);
-    let mut line = String::new();
-    reader.read_line(&mut line).expect("Failed to read line");
-    let strs: Vec<&str> = line.trim().split(",").collect();
-    let prg: Vec<usize> = strs
-        .into_iter()
-        .map(|s| { s.parse::<usize>().expect("Failed to parse value") })
-        .collect();
-
-    return prg;
-}
-
-fn execute_program(program: &mut Vec<usize>) {
-    let mut i = 0;
-    while i + 3 < program.len() {
-        let op = program[i];
-        i += 1;
-        let src1 = program[i];
-        i += 1;
-        let src2 = program[i];
-        i += 1;
-        let dst = program [i];
-        i += 1;
+use intcode::{Program, RunState};
+use std::env;
+
+const MIN_INPUT: i64 = 0;
+const MAX_INPUT: i64 = 99;
+
+const DEFAULT_TARGET_OUTPUT: i64 = 19690720;
+
+// Find the noun and verb that cause the program to produce the target
+// output, by trying all possible combinations. Combinations that make the
+// program fail or stop early are skipped.
+fn find_noun_verb(program: &Program, target: i64) -> Option<(i64, i64)> {
+    for noun in MIN_INPUT..=MAX_INPUT {
+        for verb in MIN_INPUT..=MAX_INPUT {
+            let mut prg = program.clone();
+            prg.poke(1, noun);
+            prg.poke(2, verb);
+            if prg.run() != Ok(RunState::Halted) {
+                continue;
+            }
 
-        if op == OPCODE_ADD {
-            program[dst] = program[src1] + program[src2];
-        } else if op == OPCODE_MUL {
-            program[dst] = program[src1] * program[src2];
+            if prg.peek(0) == target {
+                return Some((noun, verb));
+            }
         }
     }
-}
 
-fn set_input(program: &mut Vec<usize>, noun: usize, verb: usize) {
-    program[1] = noun;
-    program[2] = verb;
+    None
 }
 
 fn main() {
-    let orig_prg = get_program("input");
-
-    for n in MIN_INPUT..=MAX_INPUT {
-        for v in MIN_INPUT..=MAX_INPUT {
-            let mut prg = orig_prg.clone();
-            set_input(&mut prg, n, v);
-            execute_program(&mut prg);
+    let target = env::args()
+        .nth(1)
+        .map(|s| s.parse::<i64>().expect("Invalid target output"))
+        .unwrap_or(DEFAULT_TARGET_OUTPUT);
+
+    let program = Program::from_file("input");
+    match find_noun_verb(&program, target) {
+        Some((noun, verb)) => println!("Found inputs! Noun: {}, Verb: {}", noun, verb),
+        None => println!("Didn't find inputs!"),
+    }
+}
 
-            let output = prg[0];
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            if output == TARGET_OUTPUT {
-                println!("Found inputs! Noun: {noun}, Verb: {verb}", noun=n, verb=v);
-                return;
-            }
-        }
+    #[test]
+    fn noun_verb() {
+        // Sets address 0 to the sum of the values at the noun and verb.
+        let program = Program::from_str("1,0,0,0,99");
+        assert_eq!(find_noun_verb(&program, 100), Some((0, 4)));
     }
 
-    println!("Didn't find inputs!");
-    return;
+    #[test]
+    fn noun_verb_no_halt() {
+        // Without the halt the program runs off the end of memory, even
+        // though (0, 1) still sets address 0 to 1.
+        let program = Program::from_str("1,0,0,0");
+        assert_eq!(find_noun_verb(&program, 1), None);
+    }
 }
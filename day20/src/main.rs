@@ -1,11 +1,15 @@
 use pathfinding::prelude::dijkstra;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
 type Coords2D = (usize, usize);
 type Coords3D = (usize, usize, usize);
 
+// The deepest level to search in part 2 before giving up on a maze.
+const MAX_DEPTH: usize = 100;
+
 #[derive(Debug)]
 enum Tile {
     Empty,
@@ -59,8 +63,9 @@ impl Map {
         None
     }
 
-    fn from_lines(lines: &Vec<String>) -> Self {
+    fn from_lines(lines: &Vec<String>) -> Result<Self, String> {
         let mut warps: HashMap<String, Coords2D> = HashMap::new();
+        let mut label_counts: HashMap<String, usize> = HashMap::new();
         let mut warps_vec = Vec::new();
 
         let mut start = None;
@@ -98,6 +103,7 @@ impl Map {
                         let label = Map::find_tile_labels((line_idx_x, line_idx_y), &lines);
                         if label.is_some() {
                             let label = label.unwrap();
+                            *label_counts.entry(label.clone()).or_insert(0) += 1;
                             match label.as_ref() {
                                 "AA" => {
                                     start = Some((coords.0, coords.1, 0));
@@ -132,22 +138,41 @@ impl Map {
             }
         }
 
-        Map {
+        for label in &["AA", "ZZ"] {
+            let count = label_counts.remove(*label).unwrap_or(0);
+            if count != 1 {
+                return Err(format!("Expected one {} label, found {}", label, count));
+            }
+        }
+
+        // Every other label should mark the two ends of a warp.
+        let mut unpaired: Vec<String> = label_counts
+            .into_iter()
+            .filter(|(_, count)| *count != 2)
+            .map(|(label, _)| label)
+            .collect();
+        if !unpaired.is_empty() {
+            unpaired.sort();
+            return Err(format!("Unpaired warp labels: {}", unpaired.join(", ")));
+        }
+
+        Ok(Map {
             tiles: tiles,
             warps: warps_vec,
-            start: start.expect("Didn't find start tile"),
-            end: end.expect("Didn't find end tile"),
-        }
+            start: start.unwrap(),
+            end: end.unwrap(),
+        })
     }
 
-    fn from_file(filename: &str) -> Self {
+    fn from_file(filename: &str) -> Result<Self, String> {
         let file = File::open(filename).unwrap();
         let reader = BufReader::new(file);
         let lines: Vec<String> = reader.lines().map(|l| l.unwrap()).collect();
         Map::from_lines(&lines)
     }
 
-    fn get_warp_location(&self, current_coords: Coords3D, warp_coords: Coords2D, part: Part) -> Option<Coords3D> {
+    fn get_warp_location(&self, current_coords: Coords3D, warp_coords: Coords2D, part: Part,
+                         max_depth: usize) -> Option<Coords3D> {
         match part {
             Part::One => Some((warp_coords.0, warp_coords.1, current_coords.2)),
             Part::Two => {
@@ -161,20 +186,22 @@ impl Map {
                     } else {
                         None
                     }
-                } else {
+                } else if current_coords.2 < max_depth {
                     Some((warp_coords.0, warp_coords.1, current_coords.2 + 1))
+                } else {
+                    None
                 }
             },
         }
     }
 
-    fn get_neighbours(&self, coords: Coords3D, part: Part) -> Vec<Coords3D> {
+    fn get_neighbours(&self, coords: Coords3D, part: Part, max_depth: usize) -> Vec<Coords3D> {
         let mut neighbours: Vec<Coords3D> = Vec::new();
 
         // If this is a warp tile, add the other end as a neighbour.
         match self.tiles[coords.1][coords.0] {
             Tile::Warp(c) => {
-                let nbr = self.get_warp_location(coords, c, part);
+                let nbr = self.get_warp_location(coords, c, part, max_depth);
                 if nbr.is_some() {
                     neighbours.push(nbr.unwrap());
                 }
@@ -207,26 +234,147 @@ impl Map {
             .collect()
     }
 
-    fn find_path_len(&self, part: Part) -> usize {
+    // Find any floor or warp tiles that can't be reached from the start,
+    // ignoring the level rules. These usually indicate a problem with the
+    // maze, as they'll never be visited by the solver.
+    fn find_unreachable(&self) -> Vec<Coords2D> {
+        let mut visited: HashSet<Coords2D> = HashSet::new();
+        let mut stack = vec![(self.start.0, self.start.1, 0)];
+        while let Some(coords) = stack.pop() {
+            if !visited.insert((coords.0, coords.1)) {
+                continue;
+            }
+
+            stack.extend(self.get_neighbours(coords, Part::One, 0));
+        }
+
+        let mut unreachable = Vec::new();
+        for (y, row) in self.tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let walkable = matches!(tile, Tile::Floor | Tile::Warp(_));
+                if walkable && !visited.contains(&(x, y)) {
+                    unreachable.push((x, y));
+                }
+            }
+        }
+
+        unreachable
+    }
+
+    fn report_unreachable(&self) {
+        let unreachable = self.find_unreachable();
+        if unreachable.is_empty() {
+            return;
+        }
+
+        println!("{} tiles are unreachable from the start:", unreachable.len());
+        for coords in unreachable {
+            if self.warps.contains(&coords) {
+                println!("  {:?} (portal)", coords);
+            } else {
+                println!("  {:?}", coords);
+            }
+        }
+    }
+
+    // Find the shortest path from the start to the end, including both
+    // ends of each warp taken. Levels deeper than max_depth aren't
+    // searched, so this returns None if there's no path within them.
+    fn find_path(&self, part: Part, max_depth: usize) -> Option<Vec<Coords3D>> {
         let successors = |&coords: &Coords3D| -> Vec<(Coords3D, usize)> {
-            self.get_neighbours(coords, part)
+            self.get_neighbours(coords, part, max_depth)
                 .into_iter()
                 .map(|coords| (coords, 1))
                 .collect()
         };
 
         let path = dijkstra(&self.start, successors, |&coords| coords == self.end);
-        path.map(|tup| tup.1).unwrap()
+        path.map(|tup| tup.0)
+    }
+
+    fn find_path_len(&self, part: Part, max_depth: usize) -> Option<usize> {
+        self.find_path(part, max_depth).map(|path| path.len() - 1)
+    }
+
+    // Find the warps taken along a path, as pairs of the coordinates
+    // either side of each warp.
+    fn warp_hops(path: &[Coords3D]) -> Vec<(Coords3D, Coords3D)> {
+        path.windows(2)
+            .filter(|step| {
+                let (from, to) = (step[0], step[1]);
+                let dist = (from.0 as i64 - to.0 as i64).abs()
+                    + (from.1 as i64 - to.1 as i64).abs();
+                dist != 1 || from.2 != to.2
+            })
+            .map(|step| (step[0], step[1]))
+            .collect()
+    }
+
+    // Render the map once for each step of a path, with the traveller's
+    // position marked with '@'. Levels are ignored, so this is only really
+    // useful for part 1.
+    fn render_path_frames(&self, path: &[Coords3D]) -> Vec<String> {
+        path.iter()
+            .map(|&(px, py, _)| {
+                self.tiles
+                    .iter()
+                    .enumerate()
+                    .map(|(y, row)| {
+                        row.iter()
+                            .enumerate()
+                            .map(|(x, tile)| match tile {
+                                _ if (x, y) == (px, py) => '@',
+                                Tile::Empty => ' ',
+                                Tile::Wall => '#',
+                                Tile::Floor | Tile::Warp(_) => '.',
+                            })
+                            .collect::<String>()
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            })
+            .collect()
     }
 }
 
 fn main() {
-    let map = Map::from_file("input");
-    let len = map.find_path_len(Part::One);
-    println!("Shortest Path for part 1: {:?}", len);
+    let map = match Map::from_file("input") {
+        Ok(map) => map,
+        Err(e) => {
+            println!("Invalid map: {}", e);
+            return;
+        }
+    };
+    map.report_unreachable();
+
+    if env::args().any(|a| a == "--animate") {
+        let path = map.find_path(Part::One, MAX_DEPTH).expect("No path found");
+        for frame in map.render_path_frames(&path) {
+            println!("{}\n", frame);
+        }
+    }
+
+    let show_warps = env::args().any(|a| a == "--warps");
+
+    match map.find_path_len(Part::One, MAX_DEPTH) {
+        Some(len) => println!("Shortest Path for part 1: {:?}", len),
+        None => println!("No path for part 1"),
+    }
+    if show_warps {
+        if let Some(path) = map.find_path(Part::One, MAX_DEPTH) {
+            println!("Warps taken: {}", Map::warp_hops(&path).len());
+        }
+    }
 
-    let len = map.find_path_len(Part::Two);
-    println!("Shortest Path for part 2: {:?}", len);
+    match map.find_path_len(Part::Two, MAX_DEPTH) {
+        Some(len) => println!("Shortest Path for part 2: {:?}", len),
+        None => println!("No path for part 2 within {} levels", MAX_DEPTH),
+    }
+    if show_warps {
+        if let Some(path) = map.find_path(Part::Two, MAX_DEPTH) {
+            println!("Warps taken: {}", Map::warp_hops(&path).len());
+        }
+    }
 }
 
 #[cfg(test)]
@@ -255,10 +403,56 @@ mod tests {
             String::from("  ###########.#####  "),
             String::from("             Z       "),
             String::from("             Z       "),
-        ]);
+        ])
+        .unwrap();
+
+        let len = map.find_path_len(Part::One, MAX_DEPTH);
+        assert_eq!(len, Some(23));
+
+        let path = map.find_path(Part::One, MAX_DEPTH).unwrap();
+        let frames = map.render_path_frames(&path);
+        assert_eq!(frames.len(), path.len());
+
+        let last_frame: Vec<&str> = frames.last().unwrap().lines().collect();
+        assert_eq!(last_frame[map.end.1].chars().nth(map.end.0), Some('@'));
+
+        // The shortest route goes through BC, DE and FG in turn.
+        assert_eq!(
+            Map::warp_hops(&path),
+            vec![
+                ((7, 4, 0), (0, 6, 0)),
+                ((4, 8, 0), (0, 11, 0)),
+                ((0, 13, 0), (9, 10, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unreachable() {
+        let map = Map::from_lines(&vec![
+            String::from("         A           "),
+            String::from("         A           "),
+            String::from("  #######.#########  "),
+            String::from("  #######.........#  "),
+            String::from("  ##.####.#######.#  "),
+            String::from("  #######.#######.#  "),
+            String::from("  #######.#######.#  "),
+            String::from("  #####  B    ###.#  "),
+            String::from("BC...##  C    ###.#  "),
+            String::from("  ##.##       ###.#  "),
+            String::from("  ##...DE  F  ###.#  "),
+            String::from("  #####    G  ###.#  "),
+            String::from("  #########.#####.#  "),
+            String::from("DE..#######...###.#  "),
+            String::from("  #.#########.###.#  "),
+            String::from("FG..#########.....#  "),
+            String::from("  ###########.#####  "),
+            String::from("             Z       "),
+            String::from("             Z       "),
+        ])
+        .unwrap();
 
-        let len = map.find_path_len(Part::One);
-        assert_eq!(len, 23);
+        assert_eq!(map.find_unreachable(), vec![(2, 2)]);
     }
 
     #[test]
@@ -301,10 +495,11 @@ mod tests {
             String::from("  #########.###.###.#############  "),
             String::from("           B   J   C               "),
             String::from("           U   P   P               "),
-        ]);
+        ])
+        .unwrap();
 
-        let len = map.find_path_len(Part::One);
-        assert_eq!(len, 58);
+        let len = map.find_path_len(Part::One, MAX_DEPTH);
+        assert_eq!(len, Some(58));
     }
 
     #[test]
@@ -347,9 +542,68 @@ mod tests {
             String::from("  #############.#.#.###.###################  "),
             String::from("               A O F   N                     "),
             String::from("               A A D   M                     "),
+        ])
+        .unwrap();
+
+        let len = map.find_path_len(Part::Two, MAX_DEPTH);
+        assert_eq!(len, Some(396));
+    }
+
+    #[test]
+    fn pt2_unsolvable() {
+        // The first example with ZZ walled off, so part 2 can only keep
+        // going deeper through the warps.
+        let map = Map::from_lines(&vec![
+            String::from("         A           "),
+            String::from("         A           "),
+            String::from("  #######.#########  "),
+            String::from("  #######.........#  "),
+            String::from("  #######.#######.#  "),
+            String::from("  #######.#######.#  "),
+            String::from("  #######.#######.#  "),
+            String::from("  #####  B    ###.#  "),
+            String::from("BC...##  C    ###.#  "),
+            String::from("  ##.##       ###.#  "),
+            String::from("  ##...DE  F  ###.#  "),
+            String::from("  #####    G  ###.#  "),
+            String::from("  #########.#####.#  "),
+            String::from("DE..#######...###.#  "),
+            String::from("  #.#########.###.#  "),
+            String::from("FG..##########....#  "),
+            String::from("  ###########.#####  "),
+            String::from("             Z       "),
+            String::from("             Z       "),
+        ])
+        .unwrap();
+
+        assert_eq!(map.find_path_len(Part::One, 10), None);
+        assert_eq!(map.find_path_len(Part::Two, 10), None);
+    }
+
+    #[test]
+    fn unpaired_label() {
+        let map = Map::from_lines(&vec![
+            String::from("         A           "),
+            String::from("         A           "),
+            String::from("  #######.#########  "),
+            String::from("  #######.........#  "),
+            String::from("  #######.#######.#  "),
+            String::from("  #######.#######.#  "),
+            String::from("  #######.#######.#  "),
+            String::from("  #####  B    ###.#  "),
+            String::from("BC...##  C    ###.#  "),
+            String::from("  ##.##       ###.#  "),
+            String::from("  ##...DE  F  ###.#  "),
+            String::from("  #####    G  ###.#  "),
+            String::from("  #########.#####.#  "),
+            String::from("DE..#######...###.#  "),
+            String::from("  #.#########.###.#  "),
+            String::from("FH..#########.....#  "),
+            String::from("  ###########.#####  "),
+            String::from("             Z       "),
+            String::from("             Z       "),
         ]);
 
-        let len = map.find_path_len(Part::Two);
-        assert_eq!(len, 396);
+        assert_eq!(map.err(), Some(String::from("Unpaired warp labels: FG, FH")));
     }
 }
@@ -1,4 +1,6 @@
-use intcode::Program;
+use intcode::{Program, RunState};
+use std::env;
+use std::fs;
 
 #[derive(Copy, Clone)]
 enum Register {
@@ -33,6 +35,23 @@ impl Register {
 
         String::from(s)
     }
+
+    fn parse(s: &str) -> Result<Register, String> {
+        match s {
+            "A" => Ok(Register::GroundOne),
+            "B" => Ok(Register::GroundTwo),
+            "C" => Ok(Register::GroundThree),
+            "D" => Ok(Register::GroundFour),
+            "E" => Ok(Register::GroundFive),
+            "F" => Ok(Register::GroundSix),
+            "G" => Ok(Register::GroundSeven),
+            "H" => Ok(Register::GroundEight),
+            "I" => Ok(Register::GroundNine),
+            "T" => Ok(Register::Temp),
+            "J" => Ok(Register::Jump),
+            _ => Err(format!("Invalid register '{}'", s)),
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -54,11 +73,73 @@ impl Command {
             Command::Run => String::from("RUN"),
         }
     }
+
+    fn parse(line: &str) -> Result<Command, String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts.as_slice() {
+            ["NOT", o1, o2] => Ok(Command::Not(Register::parse(o1)?, Register::parse(o2)?)),
+            ["AND", o1, o2] => Ok(Command::And(Register::parse(o1)?, Register::parse(o2)?)),
+            ["OR", o1, o2] => Ok(Command::Or(Register::parse(o1)?, Register::parse(o2)?)),
+            ["WALK"] => Ok(Command::Walk),
+            ["RUN"] => Ok(Command::Run),
+            _ => Err(format!("Invalid command '{}'", line)),
+        }
+    }
 }
 
 struct SpringScript(Vec<Command>);
 
 impl SpringScript {
+    // Check the script only writes to T and J, only reads the sensors
+    // available in its mode, and ends with a single WALK or RUN.
+    fn validate(&self) -> Result<(), String> {
+        let walking = match self.0.last() {
+            Some(Command::Walk) => true,
+            Some(Command::Run) => false,
+            _ => return Err(String::from("Script must end with WALK or RUN")),
+        };
+
+        for (idx, &command) in self.0[..self.0.len() - 1].iter().enumerate() {
+            let (src, dest) = match command {
+                Command::Not(o1, o2) | Command::And(o1, o2) | Command::Or(o1, o2) => (o1, o2),
+                Command::Walk | Command::Run => {
+                    return Err(format!(
+                        "Line {}: {} must be the last command",
+                        idx + 1,
+                        command.to_string()
+                    ))
+                }
+            };
+
+            if !matches!(dest, Register::Temp | Register::Jump) {
+                return Err(format!(
+                    "Line {}: can only write to T or J, not {}",
+                    idx + 1,
+                    dest.to_string()
+                ));
+            }
+
+            // Walking only reveals the next four tiles.
+            let far_sensor = matches!(
+                src,
+                Register::GroundFive
+                    | Register::GroundSix
+                    | Register::GroundSeven
+                    | Register::GroundEight
+                    | Register::GroundNine
+            );
+            if walking && far_sensor {
+                return Err(format!(
+                    "Line {}: can't read {} when walking",
+                    idx + 1,
+                    src.to_string()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     fn to_string(&self) -> String {
         self.0
             .iter()
@@ -67,37 +148,87 @@ impl SpringScript {
             .collect::<Vec<String>>()
             .join("")
     }
+}
 
-    fn to_ascii(&self) -> Vec<u8> {
-        self.to_string().chars().map(|c| c as u8).collect()
+// Parse a springscript program, one command per line. Blank lines are
+// ignored.
+fn parse_springscript(text: &str) -> Result<SpringScript, String> {
+    let mut commands = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command = Command::parse(line).map_err(|e| format!("Line {}: {}", idx + 1, e))?;
+        commands.push(command);
     }
+
+    Ok(SpringScript(commands))
 }
 
-fn execute_springscript(program: &Program, script: &SpringScript) -> Option<i64> {
-    let buf = script.to_ascii();
-    let mut input = buf.iter();
-    let mut output = None;
-
-    program.execute_ex(
-        || {
-            let inp = input.next().unwrap();
-            print!("{}", *inp as char);
-            *inp as i64
-        },
-        |v| {
-            if v >= 128 {
-                output = Some(v);
-            } else {
-                print!("{}", (v as u8) as char);
-            }
-        },
-    );
+// Find the column the droid fell into space at, given the ASCII output of
+// a failed run. The droid's final position is the last '@' drawn in the
+// output, which sits in the hole it fell through.
+fn find_fall_point(output: &str) -> Option<usize> {
+    output.lines().rev().find_map(|line| line.find('@'))
+}
+
+// Run a springscript, returning the hull damage if the droid made it
+// across, or the droid's ASCII output if it didn't.
+fn execute_springscript(
+    program: &Program,
+    script: &SpringScript,
+    verbose: bool,
+) -> Result<i64, String> {
+    script
+        .validate()
+        .map_err(|e| format!("Invalid script: {}\n", e))?;
+
+    let mut prg = program.clone();
+    for command in &script.0 {
+        prg.feed_line(&command.to_string());
+    }
 
-    output
+    let (feed, state) = prg.read_ascii_output();
+    if verbose {
+        print!("{}", script.to_string());
+        print!("{}", feed);
+    }
+
+    match state {
+        Ok(RunState::Halted) => (),
+        Ok(state) => return Err(format!("{}Droid stopped early: {:?}\n", feed, state)),
+        Err(e) => return Err(format!("{}Droid program failed: {:?}\n", feed, e)),
+    }
+
+    prg.non_ascii_output().first().cloned().ok_or(feed)
+}
+
+fn report_damage(label: &str, result: Result<i64, String>) {
+    match result {
+        Ok(damage) => println!("{}: {}", label, damage),
+        Err(output) => {
+            print!("{}", output);
+            if let Some(column) = find_fall_point(&output) {
+                println!("Droid fell into space at column {}", column);
+            }
+        }
+    }
 }
 
 fn main() {
     let prg = Program::from_file("input");
+    let verbose = env::args().any(|a| a == "--verbose");
+
+    // Run a script from a file instead of the built-in solutions.
+    if let Some(filename) = env::args().skip_while(|a| a != "--script").nth(1) {
+        let text = fs::read_to_string(&filename).expect("Failed to read script");
+        match parse_springscript(&text) {
+            Ok(script) => report_damage("Damage", execute_springscript(&prg, &script, verbose)),
+            Err(e) => println!("Invalid script: {}", e),
+        }
+        return;
+    }
 
     // Part 1
     let script = SpringScript(vec![
@@ -113,8 +244,10 @@ fn main() {
         // Walk
         Command::Walk,
     ]);
-    let damage = execute_springscript(&prg, &script);
-    println!("Part 1 Damage: {}", damage.unwrap());
+    report_damage(
+        "Part 1 Damage",
+        execute_springscript(&prg, &script, verbose),
+    );
 
     // Part 2: Jump = !(1 && 2 && 3) && (5 || 8) && 4
     let script = SpringScript(vec![
@@ -138,6 +271,81 @@ fn main() {
         // Run
         Command::Run,
     ]);
-    let damage = execute_springscript(&prg, &script);
-    println!("Part 2 Damage: {:?}", damage.unwrap());
+    report_damage(
+        "Part 2 Damage",
+        execute_springscript(&prg, &script, verbose),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fall_point() {
+        let output = [
+            "Walking...",
+            "",
+            "Didn't make it across:",
+            "",
+            ".................",
+            ".................",
+            "@................",
+            "#####.###########",
+            "",
+            ".................",
+            ".................",
+            ".....@...........",
+            "#####.###########",
+            "",
+            ".................",
+            ".................",
+            ".................",
+            "#####@###########",
+            "",
+        ]
+        .join("\n");
+
+        assert_eq!(find_fall_point(&output), Some(5));
+        assert_eq!(find_fall_point("Walking...\n"), None);
+    }
+
+    #[test]
+    fn parse_script() {
+        let text = "OR A T\nAND B T\nNOT T J\nAND D J\nOR E T\nOR H T\nRUN\n";
+        let script = parse_springscript(text).unwrap();
+        assert_eq!(script.to_string(), text);
+
+        assert_eq!(
+            parse_springscript("NOT A J\nAND X J\n").err(),
+            Some(String::from("Line 2: Invalid register 'X'"))
+        );
+        assert_eq!(
+            parse_springscript("XOR A J\n").err(),
+            Some(String::from("Line 1: Invalid command 'XOR A J'"))
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let check = |text| parse_springscript(text).unwrap().validate();
+
+        assert_eq!(check("NOT A J\nOR H J\nRUN\n"), Ok(()));
+        assert_eq!(
+            check("NOT A J\n"),
+            Err(String::from("Script must end with WALK or RUN"))
+        );
+        assert_eq!(
+            check("NOT A J\nWALK\nRUN\n"),
+            Err(String::from("Line 2: WALK must be the last command"))
+        );
+        assert_eq!(
+            check("NOT A A\nWALK\n"),
+            Err(String::from("Line 1: can only write to T or J, not A"))
+        );
+        assert_eq!(
+            check("NOT E J\nWALK\n"),
+            Err(String::from("Line 1: can't read E when walking"))
+        );
+    }
 }
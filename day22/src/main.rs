@@ -29,6 +29,67 @@ impl ShuffleType {
     }
 }
 
+// Physically shuffle a deck of cards, for checking the coefficient-based
+// shuffles on small decks in tests.
+#[cfg(test)]
+fn deal<T: Copy>(deck: &[T], shuffle: ShuffleType) -> Vec<T> {
+    let len = deck.len();
+    let mut new_deck = deck.to_vec();
+    match shuffle {
+        ShuffleType::Stack => new_deck.reverse(),
+        ShuffleType::Cut(n) => new_deck.rotate_left(r#mod(n, len as i128) as usize),
+        ShuffleType::Increment(n) => {
+            for (i, card) in deck.iter().enumerate() {
+                new_deck[(i * n as usize) % len] = *card;
+            }
+        }
+    }
+    new_deck
+}
+
+// Physically undo a shuffle of a deck of cards.
+#[cfg(test)]
+fn undeal(deck: &[usize], shuffle: ShuffleType) -> Vec<usize> {
+    let len = deck.len();
+    let mut old_deck = deck.to_vec();
+    match shuffle {
+        ShuffleType::Stack => old_deck.reverse(),
+        ShuffleType::Cut(n) => old_deck.rotate_right(r#mod(n, len as i128) as usize),
+        ShuffleType::Increment(n) => {
+            for (i, card) in old_deck.iter_mut().enumerate() {
+                *card = deck[(i * n as usize) % len];
+            }
+        }
+    }
+    old_deck
+}
+
+#[cfg(test)]
+fn apply_to_deck<T: Copy>(deck: &[T], shuffles: &[ShuffleType]) -> Vec<T> {
+    shuffles
+        .iter()
+        .fold(deck.to_vec(), |deck, shuffle| deal(&deck, *shuffle))
+}
+
+// Shuffle a deck in factory order, giving the card at each position. Only
+// practical for small decks.
+#[cfg(test)]
+fn apply_shuffles(num_cards: i128, input: &[ShuffleType]) -> Vec<i128> {
+    let deck: Vec<i128> = (0..num_cards).collect();
+    apply_to_deck(&deck, input)
+}
+
+// Undo a sequence of shuffles on a deck in factory order, giving the deck
+// that the shuffles would put into factory order.
+#[cfg(test)]
+fn apply_inverse_to_deck(num_cards: usize, shuffles: &[ShuffleType]) -> Vec<usize> {
+    let deck: Vec<usize> = (0..num_cards).collect();
+    shuffles
+        .iter()
+        .rev()
+        .fold(deck, |deck, shuffle| undeal(&deck, *shuffle))
+}
+
 fn inverse_mod(a: i128, n: i128) -> i128 {
     let mut t = 0;
     let mut r = n;
@@ -107,7 +168,12 @@ fn combine_input(num_cards: i128, input: &Vec<ShuffleType>) -> (i128, i128) {
 }
 
 fn shuffle(num_cards: i128, input: &Vec<ShuffleType>, index: i128) -> i128 {
+    shuffle_repeat(num_cards, input, index, 1)
+}
+
+fn shuffle_repeat(num_cards: i128, input: &Vec<ShuffleType>, index: i128, repeat: i128) -> i128 {
     let muladd = combine_input(num_cards, &input);
+    let muladd = repeat_shuffle(num_cards, muladd, repeat);
     r#mod(muladd.0 * index + muladd.1, num_cards)
 }
 
@@ -258,6 +324,117 @@ mod tests {
         assert_eq!(result, 8);
     }
 
+    #[test]
+    fn physical_inverse() {
+        let shuffles = vec![
+            ShuffleType::Stack,
+            ShuffleType::Cut(-2),
+            ShuffleType::Increment(7),
+            ShuffleType::Cut(8),
+            ShuffleType::Cut(-4),
+            ShuffleType::Increment(7),
+            ShuffleType::Cut(3),
+            ShuffleType::Increment(9),
+            ShuffleType::Increment(3),
+            ShuffleType::Cut(-1),
+        ];
+        let identity: Vec<usize> = (0..10).collect();
+
+        let inverse = apply_inverse_to_deck(10, &shuffles);
+        assert_eq!(apply_to_deck(&inverse, &shuffles), identity);
+
+        // Cross-check against the coefficient-based shuffles.
+        let shuffled = apply_to_deck(&identity, &shuffles);
+        assert_eq!(shuffled, vec![9, 2, 5, 8, 1, 4, 7, 0, 3, 6]);
+        for i in 0..10 {
+            assert_eq!(
+                shuffled[i] as i128,
+                reverse_shuffle(10, &shuffles, i as i128)
+            );
+            assert_eq!(inverse[i] as i128, shuffle(10, &shuffles, i as i128));
+        }
+    }
+
+    #[test]
+    fn physical_examples() {
+        let shuffles = [
+            ShuffleType::Increment(7),
+            ShuffleType::Stack,
+            ShuffleType::Stack,
+        ];
+        assert_eq!(
+            apply_shuffles(10, &shuffles),
+            vec![0, 3, 6, 9, 2, 5, 8, 1, 4, 7]
+        );
+
+        let shuffles = [
+            ShuffleType::Cut(6),
+            ShuffleType::Increment(7),
+            ShuffleType::Stack,
+        ];
+        assert_eq!(
+            apply_shuffles(10, &shuffles),
+            vec![3, 0, 7, 4, 1, 8, 5, 2, 9, 6]
+        );
+
+        let shuffles = [
+            ShuffleType::Increment(7),
+            ShuffleType::Increment(9),
+            ShuffleType::Cut(-2),
+        ];
+        assert_eq!(
+            apply_shuffles(10, &shuffles),
+            vec![6, 3, 0, 7, 4, 1, 8, 5, 2, 9]
+        );
+
+        let shuffles = [
+            ShuffleType::Stack,
+            ShuffleType::Cut(-2),
+            ShuffleType::Increment(7),
+            ShuffleType::Cut(8),
+            ShuffleType::Cut(-4),
+            ShuffleType::Increment(7),
+            ShuffleType::Cut(3),
+            ShuffleType::Increment(9),
+            ShuffleType::Increment(3),
+            ShuffleType::Cut(-1),
+        ];
+        assert_eq!(
+            apply_shuffles(10, &shuffles),
+            vec![9, 2, 5, 8, 1, 4, 7, 0, 3, 6]
+        );
+    }
+
+    #[test]
+    fn repeat_inverse() {
+        const NUM_CARDS: i128 = 10007;
+
+        // Simple LCG, to get repeatable pseudo-random shuffles.
+        let mut seed: i128 = 12345;
+        let mut rand = |max: i128| {
+            seed = (seed * 1103515245 + 12345) % 2147483648;
+            seed % max
+        };
+
+        for _ in 0..20 {
+            let shuffles: Vec<ShuffleType> = (0..10)
+                .map(|_| match rand(3) {
+                    0 => ShuffleType::Stack,
+                    1 => ShuffleType::Cut(rand(2 * NUM_CARDS) - NUM_CARDS),
+                    _ => ShuffleType::Increment(rand(NUM_CARDS - 1) + 1),
+                })
+                .collect();
+            let index = rand(NUM_CARDS);
+            let repeat = rand(1_000_000_000) + 1;
+
+            let pos = shuffle_repeat(NUM_CARDS, &shuffles, index, repeat);
+            assert_eq!(
+                reverse_shuffle_repeat(NUM_CARDS, &shuffles, pos, repeat),
+                index
+            );
+        }
+    }
+
     #[test]
     fn ex4_reverse() {
         let shuffles = vec![
@@ -3,12 +3,16 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::env;
 
 type Packet = (i64, i64);
 type Addr = usize;
 type PacketQueue = HashMap<Addr, VecDeque<Packet>>;
 
-const NODE_COUNT: usize = 50;
+// Number of packets sent along each (source, destination) edge.
+type EdgeCounts = HashMap<(Addr, Addr), u64>;
+
+const DEFAULT_NODE_COUNT: usize = 50;
 const NAT_INPUT_ADDR: usize = 0;
 const NAT_OUTPUT_ADDR: usize = 255;
 
@@ -24,16 +28,36 @@ fn recv(node: Addr, packets: &mut PacketQueue) -> Option<Packet> {
     }
 }
 
-fn send(node: Addr, packet: Packet, packets: &mut PacketQueue) {
-    if let Some(queue) = packets.get_mut(&node) {
+fn send(src: Addr, dst: Addr, packet: Packet, packets: &mut PacketQueue, edges: &mut EdgeCounts) {
+    *edges.entry((src, dst)).or_insert(0) += 1;
+
+    if let Some(queue) = packets.get_mut(&dst) {
         queue.push_back(packet);
     } else {
-        packets.insert(node, VecDeque::from(vec![packet]));
+        packets.insert(dst, VecDeque::from(vec![packet]));
     }
 }
 
-fn main() {
-    let mut nodes = vec![Program::from_file("input"); NODE_COUNT];
+fn print_edge_counts(edges: &EdgeCounts) {
+    let mut edges: Vec<(&(Addr, Addr), &u64)> = edges.iter().collect();
+    edges.sort();
+
+    println!("Packet flow:");
+    for ((src, dst), count) in edges {
+        println!("  {} -> {}: {}", src, dst, count);
+    }
+}
+
+// Run the network until the NAT sends the same Y value to the idle network
+// twice, returning that value. on_first_y is called with the Y value of the
+// first packet sent to the NAT as soon as it is sent.
+fn run_network<F: FnMut(i64)>(
+    prg: &Program,
+    node_count: usize,
+    edges: &mut EdgeCounts,
+    mut on_first_y: F,
+) -> i64 {
+    let mut nodes = vec![prg.clone(); node_count];
 
     // Initialize the nodes
     for (i, p) in nodes.iter_mut().enumerate() {
@@ -51,6 +75,7 @@ fn main() {
 
     let packets = RefCell::new(HashMap::new());
     let mut nat = None;
+    let mut nat_seen = false;
     let mut nat_ys = HashSet::new();
     loop {
         let mut idle = true;
@@ -89,10 +114,16 @@ fn main() {
                         let packet = (x, y);
 
                         if addr == NAT_OUTPUT_ADDR {
+                            if !nat_seen {
+                                on_first_y(y);
+                                nat_seen = true;
+                            }
+
+                            *edges.entry((node, NAT_OUTPUT_ADDR)).or_insert(0) += 1;
                             nat = Some(packet);
                         } else {
                             let mut packets = packets.borrow_mut();
-                            send(addr, packet, &mut *packets);
+                            send(node, addr, packet, &mut *packets, edges);
                         }
 
                         send_buffer.clear();
@@ -109,25 +140,86 @@ fn main() {
         }
 
         // If nothing's sending packets and there are no packets left to be processed,
-        // inject a packet from the NAT.
-        idle = idle
-            && packets
-                .borrow()
-                .iter()
-                .fold(true, |acc, (k, q): (&Addr, &VecDeque<Packet>)| {
-                    acc && q.is_empty()
-                });
+        // inject a packet from the NAT. This is only checked once every node has had
+        // a turn, and a node that has just received a packet doesn't count as busy
+        // until it starts sending, so the NAT may wake the network a round early.
+        idle = idle && packets.borrow().values().all(|q| q.is_empty());
         if idle && nat.is_some() {
             // Nothing sending and all packet queues are empty.
             if nat_ys.contains(&nat.unwrap().1) {
-                println!("Result: {}", nat.unwrap().1);
-                break;
+                return nat.unwrap().1;
             } else {
                 nat_ys.insert(nat.unwrap().1);
                 let mut packets = packets.borrow_mut();
-                send(NAT_INPUT_ADDR, nat.unwrap(), &mut *packets);
+                send(
+                    NAT_OUTPUT_ADDR,
+                    NAT_INPUT_ADDR,
+                    nat.unwrap(),
+                    &mut *packets,
+                    edges,
+                );
                 nat = None;
             }
         }
     }
 }
+
+fn main() {
+    let prg = Program::from_file("input");
+    let node_count = env::args().nth(1).map_or(DEFAULT_NODE_COUNT, |s| {
+        s.parse::<usize>().expect("Invalid node count")
+    });
+
+    let mut edges = HashMap::new();
+    let result = run_network(&prg, node_count, &mut edges, |y| {
+        println!("First Y sent to NAT: {}", y)
+    });
+    println!("Result: {}", result);
+
+    print_edge_counts(&edges);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edge_counts() {
+        let mut packets = HashMap::new();
+        let mut edges = HashMap::new();
+
+        send(0, 1, (1, 2), &mut packets, &mut edges);
+        send(0, 1, (3, 4), &mut packets, &mut edges);
+        send(1, 0, (5, 6), &mut packets, &mut edges);
+        send(0, 1, (7, 8), &mut packets, &mut edges);
+
+        assert_eq!(edges.get(&(0, 1)), Some(&3));
+        assert_eq!(edges.get(&(1, 0)), Some(&1));
+        assert_eq!(edges.len(), 2);
+
+        assert_eq!(recv(1, &mut packets), Some((1, 2)));
+        assert_eq!(recv(0, &mut packets), Some((5, 6)));
+    }
+
+    #[test]
+    fn two_nodes() {
+        // Node 0 sends (7, 8) to node 1 at startup, then forwards anything it
+        // receives to node 1. Node 1 forwards anything it receives to the NAT.
+        let prg = Program::from_str(
+            "3,100,1005,100,31,104,1,104,7,104,8,\
+             3,101,1008,101,-1,102,1005,102,11,3,103,104,1,4,101,4,103,1105,1,11,\
+             3,101,1008,101,-1,102,1005,102,31,3,103,104,255,4,101,4,103,1105,1,31",
+        );
+
+        let mut edges = HashMap::new();
+        let mut first_y = None;
+        let result = run_network(&prg, 2, &mut edges, |y| first_y = Some(y));
+        assert_eq!(first_y, Some(8));
+        assert_eq!(result, 8);
+
+        assert_eq!(edges.get(&(0, 1)), Some(&2));
+        assert_eq!(edges.get(&(1, NAT_OUTPUT_ADDR)), Some(&2));
+        assert_eq!(edges.get(&(NAT_OUTPUT_ADDR, 0)), Some(&1));
+        assert_eq!(edges.len(), 3);
+    }
+}
@@ -1,10 +1,17 @@
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::env;
 use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-const MAP_SIZE: usize = 5;
+// The size of each level of a recursive map. The neighbour logic between
+// levels assumes this is 5.
+const RECURSIVE_MAP_SIZE: usize = 5;
+
+// The biodiversity rating has a bit per tile, so it only fits in a u64 for
+// maps with up to 64 tiles.
+const MAX_MAP_TILES: usize = 64;
 
 #[derive(Copy, Clone, Debug)]
 enum Tile {
@@ -41,12 +48,20 @@ type Coords = (usize, usize);
 #[derive(Clone)]
 struct Map {
     tiles: Vec<Vec<Tile>>,
+    width: usize,
+    height: usize,
+    // Whether the edges of the map wrap around, so that the top row
+    // neighbours the bottom row and the left column neighbours the right.
+    wrap: bool,
 }
 
 impl Map {
-    fn empty() -> Self {
+    fn empty(size: usize) -> Self {
         Map {
-            tiles: vec![vec![Tile::Empty; MAP_SIZE]; MAP_SIZE],
+            tiles: vec![vec![Tile::Empty; size]; size],
+            width: size,
+            height: size,
+            wrap: false,
         }
     }
 
@@ -58,7 +73,24 @@ impl Map {
             tiles.push(row);
         }
 
-        Map { tiles: tiles }
+        let height = tiles.len();
+        let width = tiles.first().map_or(0, |row: &Vec<Tile>| row.len());
+        assert!(
+            tiles.iter().all(|row| row.len() == width),
+            "Map rows have different lengths"
+        );
+        assert!(
+            width * height <= MAX_MAP_TILES,
+            "Maps can have at most {} tiles",
+            MAX_MAP_TILES
+        );
+
+        Map {
+            tiles: tiles,
+            width,
+            height,
+            wrap: false,
+        }
     }
 
     fn from_file(filename: &str) -> Self {
@@ -150,19 +182,32 @@ impl Map {
             .count()
     }
 
+    fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
     fn get_neighbour_coords(&self, coords: Coords) -> Vec<Coords> {
         let mut neighbours = Vec::new();
 
+        if self.wrap {
+            let (width, height) = (self.width, self.height);
+            neighbours.push(((coords.0 + width - 1) % width, coords.1));
+            neighbours.push(((coords.0 + 1) % width, coords.1));
+            neighbours.push((coords.0, (coords.1 + height - 1) % height));
+            neighbours.push((coords.0, (coords.1 + 1) % height));
+            return neighbours;
+        }
+
         if coords.0 > 0 {
             neighbours.push((coords.0 - 1, coords.1));
         }
-        if coords.0 < self.tiles[0].len() - 1 {
+        if coords.0 < self.width - 1 {
             neighbours.push((coords.0 + 1, coords.1));
         }
         if coords.1 > 0 {
             neighbours.push((coords.0, coords.1 - 1));
         }
-        if coords.1 < self.tiles.len() - 1 {
+        if coords.1 < self.height - 1 {
             neighbours.push((coords.0, coords.1 + 1));
         }
 
@@ -201,11 +246,10 @@ impl Map {
 
     fn evolve_infinite(&mut self, inner: Option<&Map>, outer: Option<&Map>) {
         let mut new_tiles = Vec::new();
-        for y in 0..self.tiles.len() {
-            let old_row = &self.tiles[y];
+        for y in 0..self.height {
             let mut new_row = Vec::new();
 
-            for x in 0..old_row.len() {
+            for x in 0..self.width {
                 let coords = (x, y);
 
                 if inner.is_some() && coords == (2, 2) {
@@ -261,24 +305,39 @@ impl fmt::Debug for Map {
 
 #[derive(Debug)]
 struct InfiniteMap {
+    // Levels from the innermost to the outermost.
     levels: VecDeque<Map>,
+    // The index of the starting level in levels.
+    origin: usize,
 }
 
 impl InfiniteMap {
-    fn from_lines(lines: &Vec<String>) -> Self {
+    fn from_map(map: Map) -> Self {
+        assert!(
+            map.width == RECURSIVE_MAP_SIZE && map.height == RECURSIVE_MAP_SIZE,
+            "Recursive maps must be {}x{}",
+            RECURSIVE_MAP_SIZE,
+            RECURSIVE_MAP_SIZE
+        );
+
         InfiniteMap {
-            levels: VecDeque::from(vec![Map::from_lines(lines)]),
+            levels: VecDeque::from(vec![map]),
+            origin: 0,
         }
     }
+
+    fn from_lines(lines: &Vec<String>) -> Self {
+        Self::from_map(Map::from_lines(lines))
+    }
+
     fn from_file(filename: &str) -> Self {
-        InfiniteMap {
-            levels: VecDeque::from(vec![Map::from_file(filename)]),
-        }
+        Self::from_map(Map::from_file(filename))
     }
 
     fn evolve(&mut self) {
-        self.levels.push_front(Map::empty());
-        self.levels.push_back(Map::empty());
+        self.levels.push_front(Map::empty(RECURSIVE_MAP_SIZE));
+        self.levels.push_back(Map::empty(RECURSIVE_MAP_SIZE));
+        self.origin += 1;
 
         let mut new_levels = VecDeque::new();
 
@@ -308,21 +367,66 @@ impl InfiniteMap {
             .iter()
             .fold(0, |acc, map| acc + map.count_bugs())
     }
+
+    // Find the level and coordinates of every bug. Levels are relative to the
+    // starting level, with inner levels positive and outer levels negative.
+    fn bug_positions(&self) -> Vec<(isize, usize, usize)> {
+        let mut positions = Vec::new();
+        for (i, map) in self.levels.iter().enumerate() {
+            let level = self.origin as isize - i as isize;
+            for (y, row) in map.tiles.iter().enumerate() {
+                for (x, tile) in row.iter().enumerate() {
+                    if tile.is_bug() {
+                        positions.push((level, x, y));
+                    }
+                }
+            }
+        }
+
+        positions
+    }
 }
 
 fn main() {
+    let filename = env::args()
+        .skip_while(|a| a != "--input")
+        .nth(1)
+        .unwrap_or_else(|| String::from("input"));
+
     // Part 1
-    let mut map = Map::from_file("input");
+    let mut map = Map::from_file(&filename);
     map.evolve_til_stable();
     println!("Part 1: Biodiversity {}", map.biodiversity());
 
+    if env::args().any(|a| a == "--wrap") {
+        let mut map = Map::from_file(&filename);
+        map.set_wrap(true);
+        map.evolve_til_stable();
+        println!("Part 1 (wrapping): Biodiversity {}", map.biodiversity());
+    }
+
     // Part 2
-    let mut inf_map = InfiniteMap::from_file("input");
-    const EVOLUTIONS: isize = 200;
-    for _ in 0..EVOLUTIONS {
+    const EVOLUTIONS: usize = 200;
+    let minutes = env::args()
+        .skip_while(|a| a != "--minutes")
+        .nth(1)
+        .map_or(EVOLUTIONS, |s| s.parse::<usize>().expect("Invalid minutes"));
+
+    let mut inf_map = InfiniteMap::from_file(&filename);
+    for _ in 0..minutes {
         inf_map.evolve();
     }
-    println!("Part 2: Bugs {}", inf_map.count_bugs());
+    println!(
+        "Part 2: Bugs after {} minutes {}",
+        minutes,
+        inf_map.count_bugs()
+    );
+
+    if env::args().any(|a| a == "--positions") {
+        for (level, x, y) in inf_map.bug_positions() {
+            println!("  Level {}: ({}, {})", level, x, y);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -343,6 +447,47 @@ mod tests {
         assert_eq!(map.biodiversity(), 2129920);
     }
 
+    #[test]
+    fn part1_small() {
+        let mut map = Map::from_lines(&vec![
+            String::from("#.."),
+            String::from(".#."),
+            String::from("..#"),
+        ]);
+
+        map.evolve_til_stable();
+        assert_eq!(map.biodiversity(), 170);
+    }
+
+    #[test]
+    #[should_panic(expected = "Maps can have at most 64 tiles")]
+    fn too_many_tiles() {
+        Map::from_lines(&vec![String::from("........."); 9]);
+    }
+
+    #[test]
+    fn wrap() {
+        let lines = vec![
+            String::from("#...."),
+            String::from("....."),
+            String::from("....."),
+            String::from("....."),
+            String::from("....."),
+        ];
+
+        let map = Map::from_lines(&lines);
+        assert_eq!(map.get_neighbour_coords((0, 0)).len(), 2);
+        assert_eq!(map.get_neighbour_bug_count((4, 0)), 0);
+        assert_eq!(map.get_neighbour_bug_count((0, 4)), 0);
+
+        let mut map = Map::from_lines(&lines);
+        map.set_wrap(true);
+        assert_eq!(map.get_neighbour_coords((0, 0)).len(), 4);
+        assert_eq!(map.get_neighbour_bug_count((4, 0)), 1);
+        assert_eq!(map.get_neighbour_bug_count((0, 4)), 1);
+        assert_eq!(map.get_neighbour_bug_count((4, 4)), 0);
+    }
+
     #[test]
     fn part2() {
         let mut inf_map = InfiniteMap::from_lines(&vec![
@@ -357,4 +502,36 @@ mod tests {
         }
         assert_eq!(inf_map.count_bugs(), 99);
     }
+
+    #[test]
+    fn part2_positions() {
+        let mut inf_map = InfiniteMap::from_lines(&vec![
+            String::from("....#"),
+            String::from("#..#."),
+            String::from("#..##"),
+            String::from("..#.."),
+            String::from("#...."),
+        ]);
+        for _ in 0..10 {
+            inf_map.evolve();
+        }
+
+        let positions = inf_map.bug_positions();
+        assert_eq!(positions.len(), 99);
+
+        // Depth 0 from the example walkthrough.
+        let mut level0: Vec<(usize, usize)> = positions
+            .iter()
+            .filter(|(level, _, _)| *level == 0)
+            .map(|&(_, x, y)| (x, y))
+            .collect();
+        level0.sort();
+        assert_eq!(level0, vec![(1, 0), (1, 1), (1, 2), (3, 1), (4, 1)]);
+
+        // Bugs have spread five levels in each direction.
+        assert_eq!(positions.iter().map(|p| p.0).min(), Some(-5));
+        assert_eq!(positions.iter().map(|p| p.0).max(), Some(5));
+        assert!(positions.contains(&(-5, 2, 0)));
+        assert!(positions.contains(&(5, 0, 0)));
+    }
 }
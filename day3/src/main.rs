@@ -54,19 +54,22 @@ fn find_intersection(e1: &Edge, e2: &Edge) -> Option<(i64, i64)> {
     return None;
 }
 
-// Return the sum of the distances along both wires for each intersection on two wires.
-fn find_intersections(w1: &Vec<Edge>, w2: &Vec<Edge>) -> Vec<i64> {
+// Return each intersection on two wires, along with the sum of the distances along
+// both wires to reach it. The wires both start at the origin, which isn't counted
+// as an intersection.
+fn find_intersections(w1: &Vec<Edge>, w2: &Vec<Edge>) -> Vec<((i64, i64), i64)> {
     let mut intersections = Vec::new();
     let mut w1_dist = 0;
     for e1 in w1 {
         let mut w2_dist = 0;
         for e2 in w2 {
             match find_intersection(e1, e2) {
+                Some((0, 0)) => (),
                 Some(i) => {
                     // Find the distance along the two wires - i.e. the distance along all completed
                     // edges so far, plus the partial distance along the intersecting edges.
                     let dist = w1_dist + e1.distance_along(i) + w2_dist + e2.distance_along(i);
-                    intersections.push(dist);
+                    intersections.push((i, dist));
                 },
                 None => ()
             };
@@ -104,32 +107,110 @@ fn parse_wire(edges: &[String]) -> Vec<Edge> {
     return graph;
 }
 
+// Parse wires from lines of comma-separated edges, one wire per line.
+fn parse_wires_from_strings(lines: &[String]) -> Vec<Vec<Edge>> {
+    lines
+        .iter()
+        .map(|line| {
+            let edges: Vec<String> = line.trim().split(',').map(String::from).collect();
+            parse_wire(&edges)
+        })
+        .collect()
+}
+
 fn read_wires() -> Vec<Vec<Edge>> {
     let file = File::open("input").expect("Failed to open file");
     let reader = BufReader::new(file);
 
-    let mut wires = Vec::new();
-    for line in reader.lines() {
-        let line = line.expect("Failed to read line");
-        let edges: Vec<String> = line.trim().split(",").map(|s| String::from(s)).collect();
-        let wire = parse_wire(&edges);
-        wires.push(wire);
+    let lines: Vec<String> = reader
+        .lines()
+        .map(|line| line.expect("Failed to read line"))
+        .collect();
+    parse_wires_from_strings(&lines)
+}
+
+fn manhattan_distance(p: (i64, i64)) -> i64 {
+    p.0.abs() + p.1.abs()
+}
+
+// Find the intersections between every pair of wires.
+fn find_all_intersections(wires: &[Vec<Edge>]) -> Vec<((i64, i64), i64)> {
+    let mut intersections = Vec::new();
+    for (i, w1) in wires.iter().enumerate() {
+        for w2 in &wires[i + 1..] {
+            intersections.extend(find_intersections(w1, w2));
+        }
     }
+    intersections
+}
+
+// Find the Manhattan distance of the intersection closest to the origin, across
+// all pairs of wires.
+fn best_intersection(wires: &[Vec<Edge>]) -> Option<i64> {
+    find_all_intersections(wires)
+        .iter()
+        .map(|(p, _)| manhattan_distance(*p))
+        .min()
+}
 
-    return wires;
+// Find the fewest combined steps to reach an intersection, across all pairs of wires.
+fn fewest_steps(wires: &[Vec<Edge>]) -> Option<i64> {
+    find_all_intersections(wires).iter().map(|(_, d)| *d).min()
 }
 
 fn main() {
     let wires = read_wires();
-    let wire_a = &wires[0];
-    let wire_b = &wires[1];
 
-    let intersections: Vec<i64> = find_intersections(wire_a, wire_b);
-    let result = intersections
-        .into_iter()
-        .filter(|d| *d > 0)
-        .min()
-        .expect("No intersections");
+    // Part 1
+    let result = best_intersection(&wires).expect("No intersections");
+    println!("Closest intersection: {}", result);
 
-    println!("Result: {}", result);
+    // Part 2
+    let result = fewest_steps(&wires).expect("No intersections");
+    println!("Fewest combined steps: {}", result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_wires(lines: &[&str]) -> Vec<Vec<Edge>> {
+        let lines: Vec<String> = lines.iter().map(|s| String::from(*s)).collect();
+        parse_wires_from_strings(&lines)
+    }
+
+    #[test]
+    fn example() {
+        let wires = make_wires(&["R8,U5,L5,D3", "U7,R6,D4,L4"]);
+        assert_eq!(best_intersection(&wires), Some(6));
+        assert_eq!(fewest_steps(&wires), Some(30));
+    }
+
+    #[test]
+    fn example_2() {
+        let wires = make_wires(&[
+            "R75,D30,R83,U83,L12,D49,R71,U7,L72",
+            "U62,R66,U55,R34,D71,R55,D58,R83",
+        ]);
+        assert_eq!(best_intersection(&wires), Some(159));
+        assert_eq!(fewest_steps(&wires), Some(610));
+    }
+
+    #[test]
+    fn example_3() {
+        let wires = make_wires(&[
+            "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51",
+            "U98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+        ]);
+        assert_eq!(best_intersection(&wires), Some(135));
+        assert_eq!(fewest_steps(&wires), Some(410));
+    }
+
+    #[test]
+    fn three_wires() {
+        // The first two wires don't cross, but each crosses the third.
+        let wires = make_wires(&["R10", "D2,R10", "U5,R3,D10"]);
+        assert_eq!(best_intersection(&wires), Some(3));
+        assert_eq!(fewest_steps(&wires), Some(16));
+    }
 }
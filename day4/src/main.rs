@@ -1,6 +1,15 @@
+use std::env;
+use std::ops::RangeInclusive;
+
 const MIN: u32 = 134564;
 const MAX: u32 = 585159;
 
+#[derive(Copy, Clone)]
+enum Part {
+    One,
+    Two,
+}
+
 fn to_digits(num: u32) -> Vec<u32> {
     let mut cur = num;
 
@@ -13,30 +22,111 @@ fn to_digits(num: u32) -> Vec<u32> {
     return output;
 }
 
-fn check_num(num: u32) -> bool {
+// Check digits don't decrease.
+fn is_non_decreasing(digits: &[u32]) -> bool {
+    digits.windows(2).all(|pair| pair[0] <= pair[1])
+}
+
+// Part 1: two adjacent digits are the same.
+fn check_part1(num: u32) -> bool {
     let digits = to_digits(num);
+    is_non_decreasing(&digits) && digits.windows(2).any(|pair| pair[0] == pair[1])
+}
+
+// Part 2: two adjacent digits are the same, and aren't part of a larger
+// group of matching digits.
+fn check_part2(num: u32) -> bool {
+    let digits = to_digits(num);
+    if !is_non_decreasing(&digits) {
+        return false;
+    }
 
-    let mut has_double = false;
     for i in 1..digits.len() {
-        // Check digits don't decrease.
-        if digits[i] < digits[i - 1] {
-            return false;
+        if digits[i] == digits[i - 1]
+            && !((i > 1 && digits[i - 1] == digits[i - 2])
+                || (i + 1 < digits.len() && digits[i] == digits[i + 1]))
+        {
+            return true;
         }
+    }
 
-        // Check we have a double, but not a longer sequence.
-        if digits[i] == digits[i - 1] {
-            if !((i > 1 && digits[i - 1] == digits[i - 2]) ||
-                (i + 1 < digits.len() && digits[i] == digits[i + 1])) {
-                has_double = true;
-            }
-        }
+    return false;
+}
+
+fn check_num(num: u32, part: Part) -> bool {
+    match part {
+        Part::One => check_part1(num),
+        Part::Two => check_part2(num),
     }
-    
-    return has_double;
 }
 
+fn matching_passwords_iter(range: RangeInclusive<u32>, part: Part) -> impl Iterator<Item = u32> {
+    range.filter(move |n| check_num(*n, part))
+}
+
+fn matching_passwords(range: RangeInclusive<u32>, part: Part) -> Vec<u32> {
+    matching_passwords_iter(range, part).collect()
+}
 
 fn main() {
-    let numbers: Vec<u32> = (MIN..=MAX).into_iter().filter(|n| check_num(*n)).collect();
-    println!("Result: {}", numbers.len());
+    let list = env::args().any(|a| a == "--list");
+    let mut args = env::args()
+        .skip(1)
+        .filter(|a| !a.starts_with("--"))
+        .map(|a| a.parse::<u32>().expect("Invalid range"));
+    let min = args.next().unwrap_or(MIN);
+    let max = args.next().unwrap_or(MAX);
+
+    if list {
+        let passwords = matching_passwords(min..=max, Part::One);
+        println!("Part 1 Result: {} {:?}", passwords.len(), passwords);
+        let passwords = matching_passwords(min..=max, Part::Two);
+        println!("Part 2 Result: {} {:?}", passwords.len(), passwords);
+    } else {
+        println!("Part 1 Result: {}", matching_passwords_iter(min..=max, Part::One).count());
+        println!("Part 2 Result: {}", matching_passwords_iter(min..=max, Part::Two).count());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part1() {
+        assert!(check_part1(111111));
+        assert!(check_part1(112233));
+        assert!(check_part1(123444));
+        assert!(check_part1(111122));
+        assert!(!check_part1(223450));
+        assert!(!check_part1(123789));
+    }
+
+    #[test]
+    fn part2() {
+        assert!(check_part2(112233));
+        assert!(!check_part2(123444));
+        assert!(check_part2(111122));
+        assert!(!check_part2(223450));
+    }
+
+    #[test]
+    fn list() {
+        let passwords = matching_passwords(111110..=111130, Part::One);
+        let expected: Vec<u32> = (111111..=111119).chain(111122..=111129).collect();
+        assert_eq!(passwords, expected);
+
+        let passwords = matching_passwords(111110..=111130, Part::Two);
+        assert_eq!(passwords, vec![111122]);
+    }
+
+    #[test]
+    fn iter_matches_vec() {
+        for part in &[Part::One, Part::Two] {
+            let vec = matching_passwords(111100..=112300, *part);
+            let iter: Vec<u32> = matching_passwords_iter(111100..=112300, *part).collect();
+            assert!(!vec.is_empty());
+            assert_eq!(iter, vec);
+        }
+    }
 }
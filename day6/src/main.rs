@@ -1,36 +1,102 @@
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::mem;
 
 struct Body {
     label: String,
     satellites: Vec<Body>,
 }
 
-fn build_tree(label: &String, edges: &HashMap<String, Vec<String>>) -> Body {
-    let mut satellites = Vec::new();
-    if let Some(sat_labels) = edges.get(label) {
-        for sat_label in sat_labels {
-            satellites.push(build_tree(sat_label, edges));
+// The default drop would recurse once per level of the tree, so flatten the
+// satellites instead.
+impl Drop for Body {
+    fn drop(&mut self) {
+        let mut stack = mem::take(&mut self.satellites);
+        while let Some(mut body) = stack.pop() {
+            stack.append(&mut body.satellites);
         }
     }
-
-    return Body {
-        label: label.clone(),
-        satellites: satellites,
-    };
 }
 
-// Build a tree of orbits from the input file.
-fn parse_input(filename: &str) -> Body {
-    let file = File::open(filename).expect("Failed to open file");
-    let reader = BufReader::new(file);
+// Build the tree without recursing, as the chain of orbits can be deep enough
+// to overflow the stack. Fails if the root is missing, if any body can't be
+// reached from the root, if a body orbits more than one other body, or if the
+// orbits contain a cycle.
+fn build_tree(label: &str, edges: &HashMap<String, Vec<String>>) -> Result<Body, String> {
+    if !edges.contains_key(label) {
+        return Err(format!("No body labelled {}", label));
+    }
+
+    // Walk the tree depth-first, recording each body along with the index of
+    // its parent, so that every body is listed after its parent.
+    let mut order: Vec<(&str, Option<usize>)> = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = vec![(label, None)];
+    while let Some((label, parent)) = stack.pop() {
+        if !visited.insert(label) {
+            // Reaching a body twice is a cycle if the body is one of its own
+            // ancestors, otherwise it has more than one parent.
+            let mut ancestor: Option<usize> = parent;
+            while let Some(index) = ancestor {
+                if order[index].0 == label {
+                    return Err(format!("Orbits contain a cycle through {}", label));
+                }
+                ancestor = order[index].1;
+            }
+
+            let mut parents: Vec<&str> = edges
+                .iter()
+                .filter(|(_, outers)| outers.iter().any(|l| l == label))
+                .map(|(inner, _)| inner.as_str())
+                .collect();
+            parents.sort();
+            return Err(format!(
+                "{} orbits more than one body: {}",
+                label,
+                parents.join(", ")
+            ));
+        }
+
+        let index = order.len();
+        order.push((label, parent));
+        if let Some(sat_labels) = edges.get(label) {
+            stack.extend(sat_labels.iter().map(|l| (l.as_str(), Some(index))));
+        }
+    }
+
+    let orphans = find_orphans(label, edges);
+    if !orphans.is_empty() {
+        return Err(format!(
+            "Bodies with no path to {}: {}",
+            label,
+            orphans.join(", ")
+        ));
+    }
+
+    // Attach the bodies to their parents in reverse order, so each body has
+    // all of its satellites by the time it's attached.
+    let mut bodies: Vec<Body> = order
+        .iter()
+        .map(|(label, _)| Body {
+            label: label.to_string(),
+            satellites: Vec::new(),
+        })
+        .collect();
+    for (_, parent) in order.iter().skip(1).rev() {
+        let body = bodies.pop().unwrap();
+        bodies[parent.unwrap()].satellites.push(body);
+    }
+
+    Ok(bodies.pop().unwrap())
+}
 
+// Parse lines of the form "A)B" into a map of each body to its satellites.
+fn parse_edges(lines: &[String]) -> HashMap<String, Vec<String>> {
     let mut edges: HashMap<String, Vec<String>> = HashMap::new();
     let re = Regex::new(r"(?P<inner>.*)\)(?P<outer>.*)").unwrap();
-    for line in reader.lines() {
-        let line = line.expect("Failed to read line");
+    for line in lines {
         let line = line.trim();
         let caps = re.captures(line).expect("Malformed line");
         let inner = String::from(&caps["inner"]);
@@ -43,47 +109,191 @@ fn parse_input(filename: &str) -> Body {
         }
     }
 
-    let root_label = String::from("COM");
-    return build_tree(&root_label, &edges);
+    edges
+}
+
+// Find any bodies that have no path to the root, which would be missing
+// from the tree of orbits.
+fn find_orphans(root_label: &str, edges: &HashMap<String, Vec<String>>) -> Vec<String> {
+    let mut reachable = HashSet::new();
+    let mut stack = vec![root_label];
+    while let Some(label) = stack.pop() {
+        if reachable.insert(label) {
+            if let Some(sat_labels) = edges.get(label) {
+                stack.extend(sat_labels.iter().map(|l| l.as_str()));
+            }
+        }
+    }
+
+    let mut orphans: Vec<String> = edges
+        .iter()
+        .flat_map(|(inner, outers)| std::iter::once(inner).chain(outers))
+        .filter(|l| !reachable.contains(l.as_str()))
+        .cloned()
+        .collect();
+    orphans.sort();
+    orphans.dedup();
+    orphans
+}
+
+// Build a tree of orbits from the input file.
+fn parse_input(filename: &str) -> Result<Body, String> {
+    let file = File::open(filename).expect("Failed to open file");
+    let reader = BufReader::new(file);
+    let lines: Vec<String> = reader
+        .lines()
+        .map(|l| l.expect("Failed to read line"))
+        .collect();
+    let edges = parse_edges(&lines);
+
+    build_tree("COM", &edges)
+}
+
+// The total number of direct and indirect orbits is the sum of the depths of
+// every body in the tree.
+fn count_orbits(body: &Body, depth: u32) -> u32 {
+    let mut total = 0;
+    let mut stack = vec![(body, depth)];
+    while let Some((body, depth)) = stack.pop() {
+        total += depth;
+        stack.extend(body.satellites.iter().map(|s| (s, depth + 1)));
+    }
+
+    total
 }
 
 // The minimal orbital transfer distance between us and santa is
 // found by finding the lowest common ancestor of those two nodes
 // in the tree of orbits, and summing the distance between the
 // us/santa nodes and the LCA.
-fn find_lca_distance(tree: &Body, depth: u32) -> Option<(u32)> {
-    match tree.label.as_ref() {
-        "SAN" => return Some(depth),
-        "YOU" => return Some(depth),
-        _ => {
-            let results: Vec<u32> = tree
-                .satellites
-                .iter()
-                .filter_map(|s| find_lca_distance(&s, depth + 1))
-                .collect();
+//
+// The tree is walked in post-order with an explicit stack rather than by
+// recursion, so deep chains of orbits can't overflow the stack.
+fn find_lca_distance(tree: &Body, depth: u32) -> Option<u32> {
+    // Bodies still to visit, with their depth and whether their satellites
+    // have already been visited.
+    let mut stack = vec![(tree, depth, false)];
+    // The result for each visited body whose parent hasn't been resolved yet.
+    let mut results: Vec<Option<u32>> = Vec::new();
 
-            return match results.len() {
-                // 2 matches: child branches have both us and santa, this is
-                // the LCA. Return the distance between the two.
-                2 => {
-                    let sum: u32 = results.iter().sum();
-                    Some(sum - depth * 2 - 2)
-                }
-                // 1 match, either one of the child branches has either us or
-                // santa, or we already found the LCA. Just return the result.`
-                1 => {
-                    let val: u32 = *results.first().unwrap();
-                    Some(val)
+    while let Some((body, depth, visited)) = stack.pop() {
+        let result = match body.label.as_ref() {
+            "SAN" => Some(depth),
+            "YOU" => Some(depth),
+            _ if !visited => {
+                stack.push((body, depth, true));
+                stack.extend(body.satellites.iter().map(|s| (s, depth + 1, false)));
+                continue;
+            }
+            _ => {
+                let first = results.len() - body.satellites.len();
+                let matches: Vec<u32> = results.drain(first..).flatten().collect();
+
+                match matches.len() {
+                    // 2 matches: child branches have both us and santa, this
+                    // is the LCA. Return the distance between the two.
+                    2 => {
+                        let sum: u32 = matches.iter().sum();
+                        Some(sum - depth * 2 - 2)
+                    }
+                    // 1 match, either one of the child branches has either us
+                    // or santa, or we already found the LCA. Just return the
+                    // result.
+                    1 => Some(matches[0]),
+                    0 => None,
+                    _ => panic!("Found more than 2 branch matches"),
                 }
-                0 => None,
-                _ => panic!("Found more than 2 branch matches"),
-            };
-        }
+            }
+        };
+
+        results.push(result);
     }
+
+    results.pop().unwrap()
 }
 
 fn main() {
-    let com = parse_input("input");
+    let com = match parse_input("input") {
+        Ok(com) => com,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    println!("Orbits: {}", count_orbits(&com, 0));
+
     let distance = find_lca_distance(&com, 0).expect("Couldn't find distance");
     println!("Distance: {}", distance);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orphans() {
+        let lines: Vec<String> = vec!["COM)B", "B)C", "X)Y", "C)D"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let edges = parse_edges(&lines);
+        assert_eq!(find_orphans("COM", &edges), vec!["X", "Y"]);
+    }
+
+    #[test]
+    fn orbits() {
+        let lines: Vec<String> = vec![
+            "COM)B", "B)C", "C)D", "D)E", "E)F", "B)G", "G)H", "D)I", "E)J", "J)K", "K)L",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        let com = build_tree("COM", &parse_edges(&lines)).unwrap();
+        assert_eq!(count_orbits(&com, 0), 42);
+    }
+
+    #[test]
+    fn deep_chain() {
+        const DEPTH: u32 = 100_000;
+        let mut lines: Vec<String> = (0..DEPTH).map(|i| format!("{}){}", i, i + 1)).collect();
+        lines.push(format!("{})YOU", DEPTH));
+        lines.push(String::from("0)SAN"));
+
+        let root = build_tree("0", &parse_edges(&lines)).unwrap();
+        assert_eq!(find_lca_distance(&root, 0), Some(DEPTH));
+    }
+
+    #[test]
+    fn malformed() {
+        let make_edges = |lines: &[&str]| {
+            let lines: Vec<String> = lines.iter().map(|l| String::from(*l)).collect();
+            parse_edges(&lines)
+        };
+
+        let edges = make_edges(&["COM)B", "B)C", "C)D", "D)B"]);
+        assert_eq!(
+            build_tree("COM", &edges).err(),
+            Some(String::from("Orbits contain a cycle through B"))
+        );
+
+        let edges = make_edges(&["A)B", "B)C"]);
+        assert_eq!(
+            build_tree("COM", &edges).err(),
+            Some(String::from("No body labelled COM"))
+        );
+
+        let edges = make_edges(&["COM)B", "X)Y"]);
+        assert_eq!(
+            build_tree("COM", &edges).err(),
+            Some(String::from("Bodies with no path to COM: X, Y"))
+        );
+
+        let edges = make_edges(&["COM)B", "COM)C", "B)D", "C)D"]);
+        assert_eq!(
+            build_tree("COM", &edges).err(),
+            Some(String::from("D orbits more than one body: B, C"))
+        );
+    }
+}
@@ -1,8 +1,3 @@
-use std::cmp;
-use std::sync::mpsc;
-use std::sync::mpsc::{Receiver, Sender};
-use std::thread;
-
 extern crate intcode;
 
 fn make_permutations(input: Vec<u32>, permutation: Vec<u32>, permutations: &mut Vec<Vec<u32>>) {
@@ -22,126 +17,149 @@ fn make_permutations(input: Vec<u32>, permutation: Vec<u32>, permutations: &mut
     }
 }
 
-fn part1() -> i64 {
-    let program = intcode::Program::from_file("input");
-
-    // Make all permutations of stage inputs.
-    let mut permutations = Vec::new();
-    make_permutations(vec![0, 1, 2, 3, 4], vec![], &mut permutations);
-
-    let mut max_output = 0;
-
-    let input_permutations = permutations;
-    for input_perm in input_permutations {
-        let mut stage_output = 0;
-
-        // Execute each stage - the input is first taken from the
-        // input permutation, and then the output from the previous stage.
-        for input in input_perm {
-            let input = [input as i64, stage_output];
-            let mut input_iter = input.iter();
-            program.execute_ex(
-                || *input_iter.next().unwrap(),
-                |output| stage_output = output,
-            );
-        }
-
-        // Check if the output from the final stage was higher than
-        // any previous permutation.
-        max_output = cmp::max(stage_output, max_output);
+// Run a linear chain of amplifiers with the given phases, returning the
+// output of the final amplifier.
+fn linear_output(amps: &[intcode::Program], phases: &[u32]) -> i64 {
+    let mut stage_output = 0;
+
+    // Execute each stage - the input is first taken from the
+    // phases, and then the output from the previous stage.
+    for (amp, &phase) in amps.iter().zip(phases) {
+        let input = [phase as i64, stage_output];
+        let mut input_iter = input.iter();
+        amp.execute_ex(
+            || *input_iter.next().unwrap(),
+            |output| stage_output = output,
+        );
     }
 
-    return max_output;
+    stage_output
 }
 
-fn spawn_amp(
-    amp: intcode::Program,
-    phase: u32,
-    input: Option<i64>,
-    tx: Sender<i64>,
-    rx: Receiver<i64>,
-) -> thread::JoinHandle<Option<i64>> {
-    return thread::spawn(move || {
-        let mut set_phase = false;
-        let mut initial_input = input;
-        let mut last_output = None;
-
-        let input_fn = || {
-            if !set_phase {
-                set_phase = true;
-                return phase as i64;
-            } else if let Some(i) = initial_input {
-                initial_input = None;
-                return i;
-            } else {
-                return rx.recv().unwrap();
-            }
-        };
-
-        let output_fn = |val| {
-            last_output = Some(val);
-
-            // The send may fail if the next amp has already
-            // halted, just ignore.
-            let _ = tx.send(val);
-        };
-
-        amp.execute_ex(input_fn, output_fn);
-        return last_output;
-    });
+// Run a feedback loop of amplifiers with the given phases, returning the last
+// output of the final amplifier.
+fn feedback_output(amps: &[intcode::Program], phases: &[u32]) -> i64 {
+    // Need to connect the amplifiers together, such that output values from one
+    // amplifier are used as the inputs for the next, with the final amplifier
+    // feeding back into the first. Each amplifier takes its phase as its
+    // first input.
+    let amps = amps
+        .iter()
+        .zip(phases)
+        .enumerate()
+        .map(|(i, (amp, &phase))| {
+            let mut amp = amp.clone();
+            amp.set_name(&format!("Amplifier {}", i + 1));
+            amp.push_input(phase as i64);
+            amp
+        })
+        .collect();
+
+    let output = intcode::pipe(amps, vec![0])
+        .unwrap_or_else(|e| panic!("Amplifiers with phases {:?} failed: {:?}", phases, e));
+    *output
+        .last()
+        .unwrap_or_else(|| panic!("No output from final amplifier with phases {:?}", phases))
 }
 
-fn part2() -> i64 {
+// Try every permutation of the given phases, returning the highest output and
+// the phases that produced it.
+fn max_output<F: Fn(&[u32]) -> i64>(phases: Vec<u32>, run: F) -> (i64, Vec<u32>) {
     // Make all permutations of stage inputs.
     let mut permutations = Vec::new();
-    make_permutations(vec![5, 6, 7, 8, 9], vec![], &mut permutations);
-
-    let amp_program = intcode::Program::from_file("input");
-
-    let mut max_output = 0;
-    for phases in permutations {
-        // Need to connect the amplifiers together, such that output values from one
-        // amplifier are used as the inputs for the next. Do this by running
-        // each amplifier's program in a separate thread, and passing values
-        // between the threads using channels.
-        let (a_tx, b_rx): (Sender<i64>, Receiver<i64>) = mpsc::channel();
-        let (b_tx, c_rx): (Sender<i64>, Receiver<i64>) = mpsc::channel();
-        let (c_tx, d_rx): (Sender<i64>, Receiver<i64>) = mpsc::channel();
-        let (d_tx, e_rx): (Sender<i64>, Receiver<i64>) = mpsc::channel();
-        let (e_tx, a_rx): (Sender<i64>, Receiver<i64>) = mpsc::channel();
-
-        let mut prg_a = amp_program.clone();
-        prg_a.set_name("Amplifier A");
-        let mut prg_b = amp_program.clone();
-        prg_b.set_name("Amplifier B");
-        let mut prg_c = amp_program.clone();
-        prg_c.set_name("Amplifier C");
-        let mut prg_d = amp_program.clone();
-        prg_d.set_name("Amplifier D");
-        let mut prg_e = amp_program.clone();
-        prg_e.set_name("Amplifier E");
-
-        let amp_a = spawn_amp(prg_a, phases[0], Some(0), a_tx, a_rx);
-        let amp_b = spawn_amp(prg_b, phases[1], None, b_tx, b_rx);
-        let amp_c = spawn_amp(prg_c, phases[2], None, c_tx, c_rx);
-        let amp_d = spawn_amp(prg_d, phases[3], None, d_tx, d_rx);
-        let amp_e = spawn_amp(prg_e, phases[4], None, e_tx, e_rx);
-
-        for amp in vec![amp_a, amp_b, amp_c, amp_d] {
-            amp.join().expect("Amplifier failed");
-        }
-
-        let output = amp_e.join().expect("No output from final amplifier");
-        max_output = cmp::max(max_output, output.unwrap());
-    }
+    make_permutations(phases, vec![], &mut permutations);
+
+    permutations
+        .into_iter()
+        .map(|phases| (run(&phases), phases))
+        .max_by_key(|(output, _)| *output)
+        .expect("No amplifiers")
+}
 
-    return max_output;
+// Find the highest output from a linear chain of amplifiers, trying every
+// permutation of the phases 0..n, where n is the number of amplifiers.
+fn max_linear_output(amps: &[intcode::Program]) -> (i64, Vec<u32>) {
+    max_output((0..amps.len() as u32).collect(), |phases| {
+        linear_output(amps, phases)
+    })
+}
+
+// Find the highest output from a feedback loop of amplifiers, trying every
+// permutation of the phases 5..5+n, where n is the number of amplifiers.
+fn max_feedback_output(amps: &[intcode::Program]) -> (i64, Vec<u32>) {
+    max_output((5..5 + amps.len() as u32).collect(), |phases| {
+        feedback_output(amps, phases)
+    })
 }
 
 fn main() {
-    let result = part1();
-    println!("Max linear output: {}", result);
+    let amps = vec![intcode::Program::from_file("input"); 5];
+
+    let (output, phases) = max_linear_output(&amps);
+    println!("Max linear output: {} (phases {:?})", output, phases);
+
+    let (output, phases) = max_feedback_output(&amps);
+    println!("Max feedback output: {} (phases {:?})", output, phases);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let result = part2();
-    println!("Max feedback output: {}", result);
+    fn make_amps(program: &str) -> Vec<intcode::Program> {
+        vec![intcode::Program::from_str(program); 5]
+    }
+
+    #[test]
+    fn linear() {
+        let amps = make_amps("3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0");
+        assert_eq!(max_linear_output(&amps), (43210, vec![4, 3, 2, 1, 0]));
+
+        let amps =
+            make_amps("3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0");
+        assert_eq!(max_linear_output(&amps), (54321, vec![0, 1, 2, 3, 4]));
+
+        let amps = make_amps(
+            "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,\
+             1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0",
+        );
+        assert_eq!(max_linear_output(&amps), (65210, vec![1, 0, 4, 3, 2]));
+    }
+
+    #[test]
+    fn feedback() {
+        let amps = make_amps(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,\
+             27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        );
+        assert_eq!(max_feedback_output(&amps), (139629729, vec![9, 8, 7, 6, 5]));
+
+        let amps = make_amps(
+            "3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,\
+             -5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,\
+             53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10",
+        );
+        assert_eq!(max_feedback_output(&amps), (18216, vec![9, 7, 8, 5, 6]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Amplifiers with phases [5, 6] failed: Deadlock")]
+    fn feedback_deadlock() {
+        // Each amplifier passes on its input plus one, then waits for one
+        // more input and halts. The first amplifier halts after the second
+        // round, leaving the second waiting forever.
+        let amps = vec![intcode::Program::from_str("3,0,3,0,1001,0,1,0,4,0,3,0,99"); 2];
+        feedback_output(&amps, &[5, 6]);
+    }
+
+    #[test]
+    fn rerun_best_phases() {
+        let amps = vec![intcode::Program::from_file("input"); 5];
+
+        let (output, phases) = max_linear_output(&amps);
+        assert_eq!(linear_output(&amps, &phases), output);
+
+        let (output, phases) = max_feedback_output(&amps);
+        assert_eq!(feedback_output(&amps, &phases), output);
+    }
 }
@@ -1,3 +1,4 @@
+use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
@@ -12,7 +13,9 @@ struct Image {
 }
 
 impl Image {
-    fn from_str(width: u32, height: u32, buf: &str) -> Image {
+    fn from_str(width: u32, height: u32, buf: &str) -> Result<Image, String> {
+        check_dimensions(width, height, buf)?;
+
         let mut pixels: Vec<u8> = Vec::new();
         let layer_size = width * height;
         let layers: u32 = buf.len() as u32 / layer_size;
@@ -31,26 +34,33 @@ impl Image {
             pixels.push(pixel);
         }
 
-        return Image {
-            width: width,
-            height: height,
-            layers: layers,
-            pixels: pixels,
-        };
-    }
-
-    fn from_file(width: u32, height: u32, filename: &str) -> Image {
-        let file = File::open(filename).expect("Failed to open file");
-        let mut reader = BufReader::new(file);
-        let mut line = String::new();
-        reader.read_line(&mut line).expect("Failed to read line");
-        return Image::from_str(width, height, line.as_ref());
+        Ok(Image {
+            width,
+            height,
+            layers,
+            pixels,
+        })
     }
 
     fn get_pixel_value(&self, x: u32, y: u32) -> u8 {
         return self.pixels[(x + self.width * y) as usize];
     }
 
+    // Render the image as lines of text, with '#' for white pixels.
+    fn render_ascii(&self) -> String {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| match self.get_pixel_value(x, y) {
+                        1 => '#',
+                        _ => ' ',
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
     fn to_file(&self, filename: &str) {
         let mut buf = image::ImageBuffer::new(self.width, self.height);
         for (x, y, pixel) in buf.enumerate_pixels_mut() {
@@ -61,7 +71,112 @@ impl Image {
     }
 }
 
+// Check that the image data is made up of whole layers of the given size.
+fn check_dimensions(width: u32, height: u32, buf: &str) -> Result<(), String> {
+    let layer_size = (width * height) as usize;
+    if layer_size == 0 {
+        return Err(format!("Invalid image dimensions {}x{}", width, height));
+    }
+    if buf.is_empty() || !buf.len().is_multiple_of(layer_size) {
+        return Err(format!(
+            "Image data length {} is not a multiple of the {}x{} layer size",
+            buf.len(),
+            width,
+            height
+        ));
+    }
+
+    Ok(())
+}
+
+// Read the line of image data from the input file.
+fn read_image_data(filename: &str) -> String {
+    let file = File::open(filename).expect("Failed to open file");
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    reader.read_line(&mut line).expect("Failed to read line");
+    String::from(line.trim())
+}
+
+// Find the layer with the fewest 0 digits, and multiply the number of 1
+// digits in that layer by the number of 2 digits.
+fn checksum(width: u32, height: u32, buf: &str) -> u32 {
+    let layer_size = (width * height) as usize;
+    let chars: Vec<char> = buf.chars().collect();
+
+    let counts = chars
+        .chunks(layer_size)
+        .map(|layer| {
+            let mut histogram = [0; 10];
+            for c in layer {
+                histogram[c.to_digit(10).unwrap() as usize] += 1;
+            }
+            histogram
+        })
+        .min_by_key(|histogram| histogram[0])
+        .expect("No image layers");
+
+    counts[1] * counts[2]
+}
+
 fn main() {
-    let img = Image::from_file(25, 6, "input");
+    let mut args = env::args().skip(1);
+    let width = args
+        .next()
+        .map_or(25, |a| a.parse::<u32>().expect("Invalid width"));
+    let height = args
+        .next()
+        .map_or(6, |a| a.parse::<u32>().expect("Invalid height"));
+    let filename = args.next().unwrap_or_else(|| String::from("input"));
+
+    let buf = read_image_data(&filename);
+    let img = match Image::from_str(width, height, &buf) {
+        Ok(img) => img,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+
+    println!("Checksum: {}", checksum(width, height, &buf));
     img.to_file("output.png");
+    println!("{}", img.render_ascii());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_ascii() {
+        let img = Image::from_str(2, 2, "0222112222120000").unwrap();
+        assert_eq!(img.render_ascii(), " #\n# ");
+
+        let img = Image {
+            width: 3,
+            height: 2,
+            layers: 1,
+            pixels: vec![1, 0, 1, 0, 1, 0],
+        };
+        assert_eq!(img.render_ascii(), "# #\n # ");
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(super::checksum(2, 2, "0222112222120000"), 4);
+    }
+
+    #[test]
+    fn bad_dimensions() {
+        assert_eq!(
+            Image::from_str(3, 2, "0222112222120000").err(),
+            Some(String::from(
+                "Image data length 16 is not a multiple of the 3x2 layer size"
+            ))
+        );
+        assert_eq!(
+            Image::from_str(0, 2, "0222112222120000").err(),
+            Some(String::from("Invalid image dimensions 0x2"))
+        );
+    }
 }
@@ -1,6 +1,43 @@
 extern crate intcode;
 
+use intcode::Program;
+use std::env;
+
+const TEST_MODE_INPUT: i64 = 1;
+const SENSOR_BOOST_INPUT: i64 = 2;
+
+// Run the BOOST program with a single input value, returning everything
+// it outputs.
+fn run_boost(program: &Program, input: i64) -> Vec<i64> {
+    let mut output = Vec::new();
+    program.execute_ex(|| input, |val| output.push(val));
+    output
+}
+
 fn main() {
-    let program = intcode::Program::from_file("input");
-    program.execute();
+    let filename = env::args().nth(1).unwrap_or_else(|| String::from("input"));
+    let program = Program::from_file(&filename);
+
+    // Part 1
+    let output = run_boost(&program, TEST_MODE_INPUT);
+    println!("BOOST keycode: {:?}", output);
+
+    // Part 2
+    let output = run_boost(&program, SENSOR_BOOST_INPUT);
+    println!("Distress signal coordinates: {:?}", output);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quine() {
+        let prg_str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let program = Program::from_str(prg_str);
+
+        let output = run_boost(&program, TEST_MODE_INPUT);
+        let output_strs: Vec<String> = output.iter().map(|v| v.to_string()).collect();
+        assert_eq!(output_strs.join(","), prg_str);
+    }
 }
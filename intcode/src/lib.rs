@@ -1,6 +1,17 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fmt;
 use std::fs::File;
+use std::hash::{Hash, Hasher};
 use std::io;
 use std::io::{BufRead, BufReader};
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 const OPCODE_ADD: i8 = 1;
 const OPCODE_MUL: i8 = 2;
@@ -13,8 +24,15 @@ const OPCODE_EQ: i8 = 8;
 const OPCODE_BASE: i8 = 9;
 const OPCODE_HALT: i8 = 99;
 
-#[derive(Copy, Clone, Debug)]
-enum Operation {
+// Loop detection hashes the memory within this many addresses either side of
+// the program counter.
+const LOOP_HASH_WINDOW: usize = 32;
+// The most states loop detection remembers before starting afresh. Any loop
+// shorter than this is still detected.
+const MAX_SEEN_STATES: usize = 100_000;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Operation {
     ADD,
     MUL,
     IN,
@@ -27,6 +45,46 @@ enum Operation {
     HALT,
 }
 
+// Every operation, in the order used to index per-operation counts.
+const OPERATIONS: [Operation; 10] = [
+    Operation::ADD,
+    Operation::MUL,
+    Operation::IN,
+    Operation::OUT,
+    Operation::JIT,
+    Operation::JIF,
+    Operation::LT,
+    Operation::EQ,
+    Operation::BASE,
+    Operation::HALT,
+];
+
+impl Operation {
+    // The index of the parameter the operation writes its result to, if any.
+    fn write_param(&self) -> Option<usize> {
+        match self {
+            Operation::ADD | Operation::MUL | Operation::LT | Operation::EQ => Some(2),
+            Operation::IN => Some(0),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Operation::ADD => "ADD",
+            Operation::MUL => "MUL",
+            Operation::IN => "IN",
+            Operation::OUT => "OUT",
+            Operation::JIT => "JIT",
+            Operation::JIF => "JIF",
+            Operation::LT => "LT",
+            Operation::EQ => "EQ",
+            Operation::BASE => "BASE",
+            Operation::HALT => "HALT",
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 enum ParameterMode {
     POSITION,
@@ -34,9 +92,75 @@ enum ParameterMode {
     RELATIVE,
 }
 
-#[derive(Copy, Clone, Debug)]
+impl fmt::Display for ParameterMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ParameterMode::POSITION => "pos",
+            ParameterMode::DIRECT => "imm",
+            ParameterMode::RELATIVE => "rel",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum ExecutionError {
     ProgramHalt,
+    // The program returned to a state it has already been in without
+    // performing any I/O in between, so it will never terminate. Contains
+    // the program counter at which the loop was detected.
+    InfiniteLoop(usize),
+    StepLimitExceeded,
+    // An ADD or MUL overflowed with checked arithmetic enabled. Contains the
+    // program counter of the instruction.
+    ArithmeticOverflow(usize),
+    // The program counter ran past the end of memory. Contains the program
+    // counter.
+    PcOutOfBounds(usize),
+    // A ring of programs run by pipe() stopped with programs still waiting
+    // for input that will never arrive.
+    Deadlock,
+}
+
+// Static information about an opcode, for tools such as disassemblers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct OpcodeInfo {
+    pub op: Operation,
+    pub param_count: usize,
+    // The index of the parameter the operation writes to, if any.
+    pub write_param: Option<usize>,
+}
+
+// Look up the operation for a raw opcode (without parameter modes), and its
+// parameters.
+pub fn opcode_info(opcode: i8) -> Option<OpcodeInfo> {
+    let (op, param_count) = match opcode {
+        OPCODE_ADD => (Operation::ADD, 3),
+        OPCODE_MUL => (Operation::MUL, 3),
+        OPCODE_IN => (Operation::IN, 1),
+        OPCODE_OUT => (Operation::OUT, 1),
+        OPCODE_JIT => (Operation::JIT, 2),
+        OPCODE_JIF => (Operation::JIF, 2),
+        OPCODE_LT => (Operation::LT, 3),
+        OPCODE_EQ => (Operation::EQ, 3),
+        OPCODE_BASE => (Operation::BASE, 1),
+        OPCODE_HALT => (Operation::HALT, 0),
+        _ => return None,
+    };
+
+    Some(OpcodeInfo {
+        op,
+        param_count,
+        write_param: op.write_param(),
+    })
+}
+
+// The reason a program stopped running when executing with queued input.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RunState {
+    Halted,
+    NeedsInput,
+    Breakpoint(usize),
 }
 
 #[derive(Debug)]
@@ -48,6 +172,15 @@ struct Instruction {
 
 impl Instruction {
     fn new(buf: &[i64], index: usize) -> Instruction {
+        match Instruction::decode(buf, index) {
+            Some(instruction) => instruction,
+            None => panic!("Invalid instruction at {}: {}", index, buf[index]),
+        }
+    }
+
+    // Decode the instruction at the given index, returning None if the value
+    // there isn't a valid opcode or its parameters run off the end of memory.
+    fn decode(buf: &[i64], index: usize) -> Option<Instruction> {
         let get_param_mode = |slot: i32| {
             let base: i64 = 10;
             let exp: u32 = (slot + 2) as u32;
@@ -59,19 +192,13 @@ impl Instruction {
         };
 
         let raw_op = (buf[index] % 100) as i8;
-        let (op, param_count) = match raw_op {
-            OPCODE_ADD => (Operation::ADD, 3),
-            OPCODE_MUL => (Operation::MUL, 3),
-            OPCODE_IN => (Operation::IN, 1),
-            OPCODE_OUT => (Operation::OUT, 1),
-            OPCODE_JIT => (Operation::JIT, 2),
-            OPCODE_JIF => (Operation::JIF, 2),
-            OPCODE_LT => (Operation::LT, 3),
-            OPCODE_EQ => (Operation::EQ, 3),
-            OPCODE_BASE => (Operation::BASE, 1),
-            OPCODE_HALT => (Operation::HALT, 0),
-            _ => panic!("Unknown opcode: {}", raw_op),
-        };
+        let OpcodeInfo {
+            op, param_count, ..
+        } = opcode_info(raw_op)?;
+
+        if index + param_count >= buf.len() {
+            return None;
+        }
 
         let mut params = Vec::new();
         let mut modes = Vec::new();
@@ -80,11 +207,29 @@ impl Instruction {
             modes.push(get_param_mode(i as i32));
         }
 
-        return Instruction {
+        Some(Instruction {
             op: op,
             params: params,
             param_modes: modes,
-        };
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.params.len() + 1
+    }
+}
+
+// Formats the instruction as assembly, e.g. "ADD [pos 5] [rel -3] -> [pos 7]".
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.op)?;
+        for (i, (param, mode)) in self.params.iter().zip(&self.param_modes).enumerate() {
+            if Some(i) == self.op.write_param() {
+                write!(f, " ->")?;
+            }
+            write!(f, " [{} {}]", mode, param)?;
+        }
+        Ok(())
     }
 }
 
@@ -104,7 +249,15 @@ fn read(mem: &Vec<i64>, param: i64, param_mode: ParameterMode, base: i64) -> i64
     return mem[addr];
 }
 
-fn write(mem: &mut Vec<i64>, value: i64, position: i64, param_mode: ParameterMode, base: i64) {
+// Write a value to memory, returning the address written to and the value
+// that was there previously.
+fn write(
+    mem: &mut Vec<i64>,
+    value: i64,
+    position: i64,
+    param_mode: ParameterMode,
+    base: i64,
+) -> (usize, i64) {
     let addr = match param_mode {
         ParameterMode::DIRECT => panic!("Attempt to write in direct mode"),
         ParameterMode::POSITION => position as usize,
@@ -114,43 +267,106 @@ fn write(mem: &mut Vec<i64>, value: i64, position: i64, param_mode: ParameterMod
     if addr >= mem.len() {
         mem.resize(addr + 1, 0);
     }
-    mem[addr] = value;
+    (addr, mem::replace(&mut mem[addr], value))
+}
+
+// Called with the program counter and the disassembled instruction before
+// each instruction is executed. Shared between clones of a program so that
+// programs can still be sent between threads.
+type TraceHook = Arc<Mutex<dyn FnMut(usize, &str) + Send>>;
+
+// A snapshot of the execution state of a program, used to step backwards
+// through execution history or to checkpoint a program mid-run.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ProgramState {
+    mem: Vec<i64>,
+    mem_offset: i64,
+    instruction_index: usize,
+    steps: u64,
+    halted: bool,
+    input: VecDeque<i64>,
 }
 
 #[derive(Clone)]
 pub struct Program {
     name: String,
     mem: Vec<i64>,
+    initial_mem: Vec<i64>,
     mem_offset: i64,
     instruction_index: usize,
+    steps: u64,
     halted: bool,
     debug: bool,
+    detect_loops: bool,
+    seen_states: HashSet<u64>,
+    input: VecDeque<i64>,
+    output: Vec<i64>,
+    non_ascii_output: Vec<i64>,
+    trace: Option<TraceHook>,
+    history: VecDeque<ProgramState>,
+    history_depth: usize,
+    breakpoints: HashSet<usize>,
+    // The program counter and step count of the last breakpoint stop, so
+    // that resuming doesn't stop at the same breakpoint again.
+    breakpoint_stop: Option<(usize, u64)>,
+    opcode_counts: [u64; OPERATIONS.len()],
+    step_limit: Option<u64>,
+    checked_arithmetic: bool,
+    watch_writes: bool,
+    write_log: Vec<(i64, i64, i64)>,
+    count_writes: bool,
+    write_counts: HashMap<usize, u64>,
 }
 
 impl Program {
     pub fn from_str(line: &str) -> Program {
-        let strs: Vec<&str> = line.trim().split(",").collect();
-        let instructions: Vec<i64> = strs
-            .into_iter()
+        let instructions: Vec<i64> = line
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
             .map(|s| s.parse::<i64>().expect("Failed to parse value"))
             .collect();
 
         return Program {
             name: String::new(),
+            initial_mem: instructions.clone(),
             mem: instructions,
             mem_offset: 0,
             instruction_index: 0,
+            steps: 0,
             halted: false,
             debug: false,
+            detect_loops: false,
+            seen_states: HashSet::new(),
+            input: VecDeque::new(),
+            output: Vec::new(),
+            non_ascii_output: Vec::new(),
+            trace: None,
+            history: VecDeque::new(),
+            history_depth: 0,
+            breakpoints: HashSet::new(),
+            breakpoint_stop: None,
+            opcode_counts: [0; OPERATIONS.len()],
+            step_limit: None,
+            checked_arithmetic: false,
+            watch_writes: false,
+            write_log: Vec::new(),
+            count_writes: false,
+            write_counts: HashMap::new(),
         };
     }
 
     pub fn from_file(filename: &str) -> Program {
+        // The program may be split over several lines, and may have trailing
+        // blank lines.
         let file = File::open(filename).expect("Failed to open file");
-        let mut reader = BufReader::new(file);
-        let mut line = String::new();
-        reader.read_line(&mut line).expect("Failed to read line");
-        return Program::from_str(line.as_ref());
+        let lines: Vec<String> = BufReader::new(file)
+            .lines()
+            .map(|l| l.expect("Failed to read line"))
+            .filter(|l| !l.trim().is_empty())
+            .collect();
+        return Program::from_str(&lines.join(","));
     }
 
     pub fn set_name(&mut self, name: &str) {
@@ -186,23 +402,468 @@ impl Program {
         // mutate the original program, and the caller can execute it again
         // with the same results.
         let mut prg = self.clone();
-        while prg.instruction_index < self.mem.len() && !prg.halted {
-            let _ = prg.step(&mut input_fn, &mut output_fn);
+        while prg.instruction_index < prg.mem.len() && !prg.halted {
+            if prg.step(&mut input_fn, &mut output_fn).is_err() {
+                break;
+            }
         }
     }
 
+    // Restore the program to the state it was in when it was loaded,
+    // discarding any execution progress and memory modifications.
+    pub fn reset(&mut self) {
+        self.mem = self.initial_mem.clone();
+        self.mem_offset = 0;
+        self.instruction_index = 0;
+        self.steps = 0;
+        self.halted = false;
+        self.seen_states.clear();
+        self.breakpoint_stop = None;
+        self.input.clear();
+        self.output.clear();
+        self.non_ascii_output.clear();
+        self.history.clear();
+        self.opcode_counts = [0; OPERATIONS.len()];
+        self.write_log.clear();
+        self.write_counts.clear();
+    }
+
     pub fn poke(&mut self, addr: i64, val: i64) {
-        write(&mut self.mem, val, addr, ParameterMode::POSITION, 0);
+        self.store(val, addr, ParameterMode::POSITION);
+    }
+
+    // Write to memory, recording the write if the write watch is enabled.
+    fn store(&mut self, value: i64, position: i64, param_mode: ParameterMode) {
+        let (addr, old) = write(&mut self.mem, value, position, param_mode, self.mem_offset);
+        if self.watch_writes {
+            self.write_log.push((addr as i64, old, value));
+        }
+        if self.count_writes {
+            *self.write_counts.entry(addr).or_insert(0) += 1;
+        }
+    }
+
+    // Record every write to memory as (address, old value, new value), for
+    // debugging programs that modify their own code.
+    pub fn enable_write_watch(&mut self) {
+        self.watch_writes = true;
+    }
+
+    pub fn write_log(&self) -> &[(i64, i64, i64)] {
+        &self.write_log
+    }
+
+    // Count the number of writes to each address, to find the hot spots in
+    // self-modifying programs.
+    pub fn enable_write_counts(&mut self) {
+        self.count_writes = true;
+    }
+
+    pub fn write_count(&self, addr: usize) -> u64 {
+        self.write_counts.get(&addr).cloned().unwrap_or(0)
+    }
+
+    pub fn peek(&self, addr: i64) -> i64 {
+        read(&self.mem, addr, ParameterMode::POSITION, 0)
+    }
+
+    // Read-only view of the program's current memory.
+    pub fn memory(&self) -> &[i64] {
+        &self.mem
+    }
+
+    pub fn memory_len(&self) -> usize {
+        self.mem.len()
+    }
+
+    // Render the program's memory as assembly, one instruction per line,
+    // prefixed by its address. Stops at the first HALT. Values that can't be
+    // decoded as an instruction are printed raw.
+    pub fn disassemble(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut index = 0;
+        while index < self.mem.len() {
+            match Instruction::decode(&self.mem, index) {
+                Some(instruction) => {
+                    lines.push(format!("{}: {}", index, instruction));
+                    if let Operation::HALT = instruction.op {
+                        break;
+                    }
+                    index += instruction.len();
+                }
+                None => {
+                    lines.push(format!("{}: {}", index, self.mem[index]));
+                    index += 1;
+                }
+            }
+        }
+
+        lines
+    }
+
+    // Check the program's instructions for writes in immediate mode, which
+    // would fail at runtime. This scans every decodable instruction from the
+    // start of the program to the end of memory, including any code after a
+    // HALT, so data mixed in with the code may be reported too. Returns the address and description of each
+    // invalid instruction.
+    pub fn validate_modes(&self) -> Result<(), Vec<(usize, String)>> {
+        let mut errors = Vec::new();
+        let mut index = 0;
+        while index < self.mem.len() {
+            match Instruction::decode(&self.mem, index) {
+                Some(instruction) => {
+                    if let Some(param) = instruction.op.write_param() {
+                        if let ParameterMode::DIRECT = instruction.param_modes[param] {
+                            errors.push((index, format!("Immediate mode write: {}", instruction)));
+                        }
+                    }
+                    index += instruction.len();
+                }
+                None => index += 1,
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
     pub fn is_halted(&self) -> bool {
         return self.halted;
     }
 
+    // The number of instructions executed since the program was loaded
+    // or reset.
+    pub fn steps_executed(&self) -> u64 {
+        self.steps
+    }
+
+    // The number of times each operation has been executed. Operations that
+    // haven't been executed are left out.
+    pub fn opcode_counts(&self) -> HashMap<&'static str, u64> {
+        OPERATIONS
+            .iter()
+            .zip(self.opcode_counts.iter())
+            .filter(|(_, &count)| count > 0)
+            .map(|(op, &count)| (op.name(), count))
+            .collect()
+    }
+
+    pub fn relative_base(&self) -> i64 {
+        self.mem_offset
+    }
+
+    pub fn program_counter(&self) -> usize {
+        self.instruction_index
+    }
+
+    // Jump to the given address. Instructions are decoded afresh on each
+    // step, so this is safe to call between steps.
+    pub fn set_program_counter(&mut self, pc: usize) {
+        self.instruction_index = pc;
+        // A jump invalidates the states seen by loop detection.
+        self.seen_states.clear();
+        self.breakpoint_stop = None;
+    }
+
+    // Run the program until it halts, giving up with an error if it
+    // executes more than `limit` instructions.
+    pub fn run_with_limit<I, O>(
+        &mut self,
+        limit: u64,
+        mut input_fn: I,
+        mut output_fn: O,
+    ) -> Result<(), ExecutionError>
+    where
+        I: FnMut() -> i64,
+        O: FnMut(i64),
+    {
+        let start = self.steps;
+        loop {
+            if self.steps - start >= limit {
+                return Err(ExecutionError::StepLimitExceeded);
+            }
+
+            if self.instruction_index >= self.mem.len() {
+                return Err(ExecutionError::PcOutOfBounds(self.instruction_index));
+            }
+
+            match self.step(&mut input_fn, &mut output_fn) {
+                Ok(()) => (),
+                Err(ExecutionError::ProgramHalt) => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.insert(pc);
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: usize) {
+        self.breakpoints.remove(&pc);
+    }
+
+    // Run the program until it halts or reaches a breakpoint. If the program
+    // was stopped at a breakpoint by the last call, that instruction is
+    // executed before checking for breakpoints again, so that execution can
+    // be resumed by calling this again.
+    pub fn run_to_breakpoint<I, O>(
+        &mut self,
+        mut input_fn: I,
+        mut output_fn: O,
+    ) -> Result<RunState, ExecutionError>
+    where
+        I: FnMut() -> i64,
+        O: FnMut(i64),
+    {
+        loop {
+            let pc = self.instruction_index;
+            if pc >= self.mem.len() {
+                return Err(ExecutionError::PcOutOfBounds(pc));
+            }
+
+            let resuming = self.breakpoint_stop == Some((pc, self.steps));
+            if !resuming && self.breakpoints.contains(&pc) {
+                self.breakpoint_stop = Some((pc, self.steps));
+                return Ok(RunState::Breakpoint(pc));
+            }
+
+            match self.step(&mut input_fn, &mut output_fn) {
+                Ok(()) => (),
+                Err(ExecutionError::ProgramHalt) => return Ok(RunState::Halted),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // Iterate over the program's output values, running the program as
+    // each value is requested. The iterator ends when the program halts.
+    pub fn outputs<I: FnMut() -> i64>(&mut self, input_fn: I) -> OutputIter<'_, I> {
+        OutputIter {
+            prg: self,
+            input_fn,
+        }
+    }
+
+    // Queue a value to be used as input by run().
+    pub fn push_input(&mut self, val: i64) {
+        self.input.push_back(val);
+    }
+
+    // Queue a line of ASCII input, followed by a newline.
+    pub fn feed_line(&mut self, line: &str) {
+        self.input.extend(line.bytes().map(i64::from));
+        self.input.push_back(i64::from(b'\n'));
+    }
+
+    // Run the program using the queued input until it halts, or until it
+    // needs input and the queue is empty. Output is buffered, and can be
+    // collected with take_output() or read_ascii_output().
+    pub fn run(&mut self) -> Result<RunState, ExecutionError> {
+        let mut input = mem::take(&mut self.input);
+        let mut output = mem::take(&mut self.output);
+
+        let result = loop {
+            if self.halted {
+                break Ok(RunState::Halted);
+            }
+
+            if self.instruction_index >= self.mem.len() {
+                break Err(ExecutionError::PcOutOfBounds(self.instruction_index));
+            }
+
+            let next_op = Instruction::decode(&self.mem, self.instruction_index).map(|i| i.op);
+            if input.is_empty() && matches!(next_op, Some(Operation::IN)) {
+                break Ok(RunState::NeedsInput);
+            }
+
+            match self.step(&mut || input.pop_front().unwrap(), &mut |val| {
+                output.push(val)
+            }) {
+                Ok(()) => (),
+                Err(ExecutionError::ProgramHalt) => break Ok(RunState::Halted),
+                Err(e) => break Err(e),
+            }
+        };
+
+        self.input = input;
+        self.output = output;
+        result
+    }
+
+    // Take all the output buffered by run().
+    pub fn take_output(&mut self) -> Vec<i64> {
+        mem::take(&mut self.output)
+    }
+
+    // Run the program until it halts or needs more input, and return its
+    // ASCII output as a string, along with the reason it stopped. Output
+    // values that aren't ASCII (such as a final numeric answer) are kept
+    // aside, see non_ascii_output(). If the program fails, whatever output
+    // it produced before the error is still returned.
+    pub fn read_ascii_output(&mut self) -> (String, Result<RunState, ExecutionError>) {
+        let state = self.run();
+
+        let mut text = String::new();
+        for val in self.output.drain(..) {
+            if (0..128).contains(&val) {
+                text.push(val as u8 as char);
+            } else {
+                self.non_ascii_output.push(val);
+            }
+        }
+
+        (text, state)
+    }
+
+    // Run a copy of the program, feeding it the given ASCII input, and return
+    // the ASCII text it outputs. Any non-ASCII output is ignored. Panics if
+    // the program fails; use read_ascii_output() to handle failures.
+    pub fn run_collect_ascii(&self, input: &str) -> String {
+        let mut prg = self.clone();
+        prg.input.extend(input.bytes().map(i64::from));
+        let (text, state) = prg.read_ascii_output();
+        if let Err(e) = state {
+            panic!("Program failed while collecting ASCII output: {:?}", e);
+        }
+
+        text
+    }
+
+    // All the non-ASCII values output so far via read_ascii_output().
+    pub fn non_ascii_output(&self) -> &[i64] {
+        &self.non_ascii_output
+    }
+
+    pub fn set_trace<F>(&mut self, hook: F)
+    where
+        F: FnMut(usize, &str) + Send + 'static,
+    {
+        self.trace = Some(Arc::new(Mutex::new(hook)));
+    }
+
+    pub fn clear_trace(&mut self) {
+        self.trace = None;
+    }
+
+    // Record up to `depth` previous states, so that execution can be stepped
+    // backwards with step_back(). A depth of 0 disables the history.
+    pub fn set_history(&mut self, depth: usize) {
+        self.history_depth = depth;
+        while self.history.len() > depth {
+            self.history.pop_front();
+        }
+    }
+
+    // Undo the most recent step, returning false if there's no history to
+    // restore. Only the execution state is rewound: the opcode counts, write
+    // log, write counts and buffered output still include the undone step.
+    pub fn step_back(&mut self) -> bool {
+        match self.history.pop_back() {
+            Some(state) => {
+                self.restore(state);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn snapshot(&self) -> ProgramState {
+        ProgramState {
+            mem: self.mem.clone(),
+            mem_offset: self.mem_offset,
+            instruction_index: self.instruction_index,
+            steps: self.steps,
+            halted: self.halted,
+            input: self.input.clone(),
+        }
+    }
+
+    fn restore(&mut self, state: ProgramState) {
+        self.mem = state.mem;
+        self.mem_offset = state.mem_offset;
+        self.instruction_index = state.instruction_index;
+        self.steps = state.steps;
+        self.halted = state.halted;
+        self.input = state.input;
+        // The states seen since the last I/O may now be ahead of us.
+        self.seen_states.clear();
+        self.breakpoint_stop = None;
+    }
+
+    pub fn save_state(&self) -> ProgramState {
+        self.snapshot()
+    }
+
+    // Create a program from a saved state. The original program isn't part
+    // of the state, so resetting the restored program restores the memory
+    // as it was when the state was saved.
+    pub fn restore_state(state: ProgramState) -> Program {
+        let mut prg = Program::from_str("");
+        prg.initial_mem = state.mem.clone();
+        prg.restore(state);
+        prg
+    }
+
     pub fn enable_debug(&mut self, enable: bool) {
         self.debug = enable;
     }
 
+    // Enable detection of infinite loops. This hashes the program counter,
+    // relative base and the memory around the program counter before every
+    // instruction, so it is off by default. Memory further away isn't
+    // considered, so a loop that only changes distant memory may be reported
+    // as infinite.
+    pub fn enable_loop_detection(&mut self, enable: bool) {
+        self.detect_loops = enable;
+        self.seen_states.clear();
+    }
+
+    // Builder-style configuration, e.g.
+    // prg.with_step_limit(1000).with_checked_arithmetic(true)
+
+    // Fail with StepLimitExceeded once the program has executed `limit`
+    // instructions in total.
+    pub fn with_step_limit(&mut self, limit: u64) -> &mut Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    // Fail with ArithmeticOverflow rather than panicking or wrapping when an
+    // ADD or MUL overflows.
+    pub fn with_checked_arithmetic(&mut self, enable: bool) -> &mut Self {
+        self.checked_arithmetic = enable;
+        self
+    }
+
+    pub fn with_loop_detection(&mut self, enable: bool) -> &mut Self {
+        self.enable_loop_detection(enable);
+        self
+    }
+
+    pub fn with_history(&mut self, depth: usize) -> &mut Self {
+        self.set_history(depth);
+        self
+    }
+
+    pub fn with_debug(&mut self, enable: bool) -> &mut Self {
+        self.enable_debug(enable);
+        self
+    }
+
+    fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.instruction_index.hash(&mut hasher);
+        self.mem_offset.hash(&mut hasher);
+        let start = self.instruction_index.saturating_sub(LOOP_HASH_WINDOW);
+        let end = (self.instruction_index + LOOP_HASH_WINDOW).min(self.mem.len());
+        self.mem[start.min(end)..end].hash(&mut hasher);
+        hasher.finish()
+    }
+
     pub fn step<I, O>(&mut self, input_fn: &mut I, output_fn: &mut O) -> Result<(), ExecutionError>
     where
         I: FnMut() -> i64,
@@ -214,6 +875,60 @@ impl Program {
             return Err(ExecutionError::ProgramHalt);
         }
 
+        if let Some(limit) = self.step_limit {
+            if self.steps >= limit {
+                return Err(ExecutionError::StepLimitExceeded);
+            }
+        }
+
+        if self.checked_arithmetic && matches!(instruction.op, Operation::ADD | Operation::MUL) {
+            let val1 = read(
+                &self.mem,
+                instruction.params[0],
+                instruction.param_modes[0],
+                self.mem_offset,
+            );
+            let val2 = read(
+                &self.mem,
+                instruction.params[1],
+                instruction.param_modes[1],
+                self.mem_offset,
+            );
+            let result = match instruction.op {
+                Operation::ADD => val1.checked_add(val2),
+                _ => val1.checked_mul(val2),
+            };
+            if result.is_none() {
+                return Err(ExecutionError::ArithmeticOverflow(self.instruction_index));
+            }
+        }
+
+        if self.detect_loops {
+            // Any I/O means the program may behave differently next time
+            // round, so only states seen since the last I/O count.
+            match instruction.op {
+                Operation::IN | Operation::OUT => self.seen_states.clear(),
+                _ => {
+                    if self.seen_states.len() >= MAX_SEEN_STATES {
+                        self.seen_states.clear();
+                    }
+                    if !self.seen_states.insert(self.state_hash()) {
+                        return Err(ExecutionError::InfiniteLoop(self.instruction_index));
+                    }
+                }
+            }
+        }
+
+        if self.history_depth > 0 {
+            if self.history.len() == self.history_depth {
+                self.history.pop_front();
+            }
+            self.history.push_back(self.snapshot());
+        }
+
+        self.steps += 1;
+        self.opcode_counts[instruction.op as usize] += 1;
+
         if self.debug {
             println!(
                 "{} {}, {:?}",
@@ -221,6 +936,11 @@ impl Program {
             );
         }
 
+        if let Some(trace) = &self.trace {
+            let mut hook = trace.lock().unwrap();
+            (*hook)(self.instruction_index, &instruction.to_string());
+        }
+
         self.instruction_index += 1;
 
         let mut binary_op = |op_fn: &dyn Fn(i64, i64) -> i64| {
@@ -236,12 +956,10 @@ impl Program {
                 instruction.param_modes[1],
                 self.mem_offset,
             );
-            write(
-                &mut self.mem,
+            self.store(
                 op_fn(val1, val2),
                 instruction.params[2],
                 instruction.param_modes[2],
-                self.mem_offset,
             );
             self.instruction_index += 3;
         };
@@ -252,12 +970,10 @@ impl Program {
             Operation::LT => binary_op(&|v1, v2| if v1 < v2 { 1 } else { 0 }),
             Operation::EQ => binary_op(&|v1, v2| if v1 == v2 { 1 } else { 0 }),
             Operation::IN => {
-                write(
-                    &mut self.mem,
+                self.store(
                     input_fn(),
                     instruction.params[0],
                     instruction.param_modes[0],
-                    self.mem_offset,
                 );
                 self.instruction_index += 1;
             }
@@ -329,6 +1045,86 @@ impl Program {
     }
 }
 
+// Run a ring of programs, with each program's output used as the input to
+// the next program, and the last program's output fed back into the first.
+// The initial input is queued for the first program, after anything already
+// queued (such as a phase setting). Returns everything the last program
+// output once every program has halted.
+//
+// The programs are run cooperatively in order: each program runs until it
+// halts or needs more input, and all of its output is queued for the next
+// program before the next program runs. Outputs are therefore always
+// delivered in the order they were produced. If no program can make progress
+// while some are still waiting for input, the ring has deadlocked and this
+// returns ExecutionError::Deadlock, discarding any output. If a program fails,
+// its error is returned.
+pub fn pipe(
+    mut programs: Vec<Program>,
+    initial_input: Vec<i64>,
+) -> Result<Vec<i64>, ExecutionError> {
+    let mut result = Vec::new();
+    if programs.is_empty() {
+        return Ok(result);
+    }
+
+    programs[0].input.extend(initial_input);
+
+    let count = programs.len();
+    let mut halted = vec![false; count];
+    loop {
+        let mut progressed = false;
+        for i in 0..count {
+            if halted[i] {
+                continue;
+            }
+
+            let steps = programs[i].steps_executed();
+            match programs[i].run()? {
+                RunState::NeedsInput => (),
+                _ => halted[i] = true,
+            }
+            progressed |= programs[i].steps_executed() != steps;
+
+            let output = programs[i].take_output();
+            if i == count - 1 {
+                result.extend(&output);
+            }
+            programs[(i + 1) % count].input.extend(output);
+        }
+
+        if halted.iter().all(|&h| h) {
+            return Ok(result);
+        }
+        if !progressed {
+            return Err(ExecutionError::Deadlock);
+        }
+    }
+}
+
+pub struct OutputIter<'a, I: FnMut() -> i64> {
+    prg: &'a mut Program,
+    input_fn: I,
+}
+
+impl<'a, I: FnMut() -> i64> Iterator for OutputIter<'a, I> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        let mut output = None;
+        while output.is_none() && self.prg.instruction_index < self.prg.mem.len() {
+            if self
+                .prg
+                .step(&mut self.input_fn, &mut |val| output = Some(val))
+                .is_err()
+            {
+                break;
+            }
+        }
+
+        output
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -425,6 +1221,569 @@ mod tests {
         assert_eq!(output, Some(1));
     }
 
+    #[test]
+    fn halt_before_end_of_memory() {
+        // The program halts at index 2, the output instructions after it
+        // must never be executed.
+        let prg = Program::from_str("104,1,99,104,2,104,3,99");
+
+        let mut output = Vec::new();
+        prg.execute_ex(|| 0, |val| output.push(val));
+        assert_eq!(output, vec![1]);
+    }
+
+    #[test]
+    fn peek_poke() {
+        let mut prg = Program::from_str("1,0,0,0,99");
+        prg.poke(3, 42);
+        assert_eq!(prg.peek(3), 42);
+
+        // Addresses beyond the allocated memory read as 0, and poking
+        // them grows the memory.
+        assert_eq!(prg.peek(100), 0);
+        prg.poke(100, 7);
+        assert_eq!(prg.peek(100), 7);
+    }
+
+    #[test]
+    fn memory_snapshot() {
+        let mut prg = Program::from_str("1,0,0,0,99");
+        prg.poke(1, 5);
+        prg.poke(2, 6);
+        assert_eq!(prg.memory(), &[1, 5, 6, 0, 99]);
+        assert_eq!(prg.memory_len(), 5);
+
+        prg.poke(6, 3);
+        assert_eq!(prg.memory(), &[1, 5, 6, 0, 99, 0, 3]);
+        assert_eq!(prg.memory_len(), 7);
+    }
+
+    #[test]
+    fn reset() {
+        let mut prg = Program::from_str("3,9,8,9,10,9,4,9,99,-1,8");
+
+        let run = |prg: &mut Program| {
+            let mut output = Vec::new();
+            while prg.step(&mut || 8, &mut |val| output.push(val)).is_ok() {}
+            output
+        };
+
+        let first = run(&mut prg);
+        assert!(prg.is_halted());
+
+        prg.reset();
+        assert!(!prg.is_halted());
+        assert_eq!(prg.memory(), &[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]);
+
+        let second = run(&mut prg);
+        assert_eq!(first, vec![1]);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn infinite_loop() {
+        // Unconditional jump back to the start.
+        let mut prg = Program::from_str("1105,1,0");
+        prg.enable_loop_detection(true);
+
+        let mut result = Ok(());
+        while result.is_ok() {
+            result = prg.step(&mut || 0, &mut |_| {});
+        }
+        assert_eq!(result, Err(ExecutionError::InfiniteLoop(0)));
+    }
+
+    #[test]
+    fn finite_loop() {
+        // Count up to 5 then halt - the counter changes on every pass, so
+        // this must not be flagged as an infinite loop.
+        let mut prg = Program::from_str("1001,12,1,12,1008,12,5,13,1006,13,0,99,0,0");
+        prg.enable_loop_detection(true);
+
+        let mut result = Ok(());
+        while result.is_ok() {
+            result = prg.step(&mut || 0, &mut |_| {});
+        }
+        assert_eq!(result, Err(ExecutionError::ProgramHalt));
+        assert_eq!(prg.peek(12), 5);
+    }
+
+    #[test]
+    fn step_back_loop_detection() {
+        let mut prg = Program::from_str("1101,0,0,9,1105,1,0,99");
+        prg.with_loop_detection(true).with_history(10);
+
+        assert_eq!(prg.step(&mut || 0, &mut |_| {}), Ok(()));
+        assert!(prg.step_back());
+        assert_eq!(prg.step(&mut || 0, &mut |_| {}), Ok(()));
+    }
+
+    #[test]
+    fn set_pc_loop_detection() {
+        let mut prg = Program::from_str("1101,0,0,9,1105,1,0,99");
+        prg.enable_loop_detection(true);
+
+        assert_eq!(prg.step(&mut || 0, &mut |_| {}), Ok(()));
+        prg.set_program_counter(0);
+        assert_eq!(prg.step(&mut || 0, &mut |_| {}), Ok(()));
+    }
+
+    #[test]
+    fn long_finite_loop() {
+        // As above, but counting far enough that the set of seen states has to
+        // be bounded.
+        let mut prg = Program::from_str("1001,12,1,12,1008,12,200000,13,1006,13,0,99,0,0");
+        prg.enable_loop_detection(true);
+
+        let mut result = Ok(());
+        while result.is_ok() {
+            result = prg.step(&mut || 0, &mut |_| {});
+            assert!(prg.seen_states.len() <= MAX_SEEN_STATES);
+        }
+        assert_eq!(result, Err(ExecutionError::ProgramHalt));
+    }
+
+    #[test]
+    fn step_limit() {
+        let mut prg = Program::from_str("1105,1,0");
+        let result = prg.run_with_limit(100, || 0, |_| {});
+        assert_eq!(result, Err(ExecutionError::StepLimitExceeded));
+        assert_eq!(prg.steps_executed(), 100);
+
+        // The day 5 comparison program executes 4 instructions, including
+        // the halt.
+        let mut prg = Program::from_str("3,9,8,9,10,9,4,9,99,-1,8");
+        let result = prg.run_with_limit(100, || 8, |_| {});
+        assert_eq!(result, Ok(()));
+        assert_eq!(prg.steps_executed(), 4);
+    }
+
+    #[test]
+    fn disassemble() {
+        let prg = Program::from_str("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+        assert_eq!(
+            prg.disassemble(),
+            vec![
+                "0: BASE [imm 1]",
+                "2: OUT [rel -1]",
+                "4: ADD [pos 100] [imm 1] -> [pos 100]",
+                "8: EQ [pos 100] [imm 16] -> [pos 101]",
+                "12: JIF [pos 101] [imm 0]",
+                "15: HALT",
+            ]
+        );
+
+        // Undecodable values and truncated instructions are printed raw.
+        let prg = Program::from_str("42,3,5,1,2");
+        assert_eq!(
+            prg.disassemble(),
+            vec!["0: 42", "1: IN -> [pos 5]", "3: 1", "4: 2"]
+        );
+    }
+
+    #[test]
+    fn parse_whitespace() {
+        let prg = Program::from_str("1, 2 ,3 ,99\n\n");
+        assert_eq!(prg.memory(), &[1, 2, 3, 99]);
+
+        let filename = std::env::temp_dir().join("intcode_parse_whitespace");
+        std::fs::write(&filename, "1, 2 ,3 ,\n99\n\n").unwrap();
+        let prg = Program::from_file(filename.to_str().unwrap());
+        std::fs::remove_file(&filename).unwrap();
+        assert_eq!(prg.memory(), &[1, 2, 3, 99]);
+    }
+
+    #[test]
+    fn queued_input() {
+        // Echo two inputs.
+        let mut prg = Program::from_str("3,0,4,0,3,0,4,0,99");
+        prg.push_input(65);
+        assert_eq!(prg.run(), Ok(RunState::NeedsInput));
+        assert_eq!(prg.take_output(), vec![65]);
+
+        prg.push_input(66);
+        assert_eq!(prg.run(), Ok(RunState::Halted));
+        assert_eq!(prg.take_output(), vec![66]);
+    }
+
+    #[test]
+    fn ascii_io() {
+        // Echo one character of input.
+        let mut prg = Program::from_str("3,0,4,0,99");
+        prg.feed_line("A");
+        assert_eq!(
+            prg.read_ascii_output(),
+            (String::from("A"), Ok(RunState::Halted))
+        );
+
+        // Echo one character, then wait for more.
+        let mut prg = Program::from_str("3,0,4,0,3,0,99");
+        prg.push_input(66);
+        assert_eq!(
+            prg.read_ascii_output(),
+            (String::from("B"), Ok(RunState::NeedsInput))
+        );
+
+        // Print "Hi" followed by a large numeric answer.
+        let mut prg = Program::from_str("104,72,104,105,104,10,104,1000,99");
+        assert_eq!(
+            prg.read_ascii_output(),
+            (String::from("Hi\n"), Ok(RunState::Halted))
+        );
+        assert_eq!(prg.non_ascii_output(), &[1000]);
+    }
+
+    #[test]
+    fn collect_ascii() {
+        // Echo input until a newline is read.
+        let prg = Program::from_str("3,100,4,100,1008,100,10,101,1006,101,0,99");
+        assert_eq!(prg.run_collect_ascii("Hello\n"), "Hello\n");
+    }
+
+    #[test]
+    #[should_panic]
+    fn collect_ascii_off_end() {
+        let prg = Program::from_str("104,65");
+        prg.run_collect_ascii("");
+    }
+
+    #[test]
+    fn run_off_end() {
+        // Output a value, then run off the end of memory without halting.
+        let mut prg = Program::from_str("104,3");
+        assert_eq!(prg.run(), Err(ExecutionError::PcOutOfBounds(2)));
+        assert_eq!(prg.take_output(), vec![3]);
+
+        let mut prg = Program::from_str("104,65");
+        assert_eq!(
+            prg.read_ascii_output(),
+            (String::from("A"), Err(ExecutionError::PcOutOfBounds(2)))
+        );
+    }
+
+    #[test]
+    fn run_with_limit_off_end() {
+        let mut prg = Program::from_str("1101,1,2,0");
+        assert_eq!(
+            prg.run_with_limit(10, || 0, |_| ()),
+            Err(ExecutionError::PcOutOfBounds(4))
+        );
+    }
+
+    #[test]
+    fn program_counter() {
+        // ADD, then a JIT with a non-taken branch, then HALT.
+        let mut prg = Program::from_str("1101,1,2,0,1105,0,0,99");
+        assert_eq!(prg.program_counter(), 0);
+
+        prg.step(&mut || 0, &mut |_| ()).unwrap();
+        assert_eq!(prg.program_counter(), 4);
+
+        prg.step(&mut || 0, &mut |_| ()).unwrap();
+        assert_eq!(prg.program_counter(), 7);
+        assert_eq!(prg.relative_base(), 0);
+
+        // Jump back to the JIT and execute it again.
+        prg.set_program_counter(4);
+        prg.step(&mut || 0, &mut |_| ()).unwrap();
+        assert_eq!(prg.program_counter(), 7);
+        assert_eq!(
+            prg.step(&mut || 0, &mut |_| ()),
+            Err(ExecutionError::ProgramHalt)
+        );
+    }
+
+    #[test]
+    fn trace() {
+        let trace = Arc::new(Mutex::new(Vec::new()));
+        let trace_clone = Arc::clone(&trace);
+
+        let mut prg = Program::from_str("1101,1,2,0,4,0,99");
+        prg.set_trace(move |pc, instr| trace_clone.lock().unwrap().push((pc, instr.to_string())));
+        prg.execute_ex(|| 0, |_| ());
+
+        let trace = trace.lock().unwrap();
+        assert_eq!(trace.len(), 3);
+        assert_eq!(
+            trace[0],
+            (0, String::from("ADD [imm 1] [imm 2] -> [pos 0]"))
+        );
+        assert_eq!(trace[2], (6, String::from("HALT")));
+    }
+
+    #[test]
+    fn output_iter() {
+        let mut prg = Program::from_str("3,0,4,0,104,2,99");
+        let mut outputs = prg.outputs(|| 1);
+        assert_eq!(outputs.next(), Some(1));
+        assert_eq!(outputs.next(), Some(2));
+        assert_eq!(outputs.next(), None);
+        assert_eq!(outputs.next(), None);
+        assert!(prg.is_halted());
+
+        // Running off the end of memory also ends the iterator.
+        let mut prg = Program::from_str("104,3");
+        assert_eq!(prg.outputs(|| 0).collect::<Vec<i64>>(), vec![3]);
+    }
+
+    #[test]
+    fn step_back() {
+        let mut prg = Program::from_str("1101,1,2,0,1101,3,4,1,1101,5,6,2,99");
+        prg.set_history(2);
+
+        for _ in 0..3 {
+            prg.step(&mut || 0, &mut |_| ()).unwrap();
+        }
+        assert_eq!(
+            prg.memory(),
+            &[3, 7, 11, 0, 1101, 3, 4, 1, 1101, 5, 6, 2, 99]
+        );
+
+        assert!(prg.step_back());
+        assert!(prg.step_back());
+        assert_eq!(prg.program_counter(), 4);
+        assert_eq!(prg.steps_executed(), 1);
+        assert_eq!(
+            prg.memory(),
+            &[3, 1, 2, 0, 1101, 3, 4, 1, 1101, 5, 6, 2, 99]
+        );
+
+        // Only two states were kept.
+        assert!(!prg.step_back());
+    }
+
+    #[test]
+    fn save_restore() {
+        let mut prg = Program::from_str("3,0,4,0,3,0,4,0,99");
+        prg.push_input(5);
+        assert_eq!(prg.run(), Ok(RunState::NeedsInput));
+        assert_eq!(prg.take_output(), vec![5]);
+
+        // Queued input is saved along with the rest of the state.
+        prg.push_input(6);
+        let state = prg.save_state();
+        assert_eq!(prg.run(), Ok(RunState::Halted));
+        assert_eq!(prg.take_output(), vec![6]);
+
+        let mut restored = Program::restore_state(state.clone());
+        assert_eq!(restored.save_state(), state);
+        assert_eq!(restored.program_counter(), 4);
+        assert_eq!(restored.run(), Ok(RunState::Halted));
+        assert_eq!(restored.take_output(), vec![6]);
+    }
+
+    #[test]
+    fn breakpoint() {
+        // Output whether the input is equal to 8, breaking before the output.
+        let mut prg = Program::from_str("3,9,8,9,10,9,4,9,99,-1,8");
+        prg.add_breakpoint(6);
+
+        let mut output = Vec::new();
+        let state = prg.run_to_breakpoint(|| 8, |val| output.push(val));
+        assert_eq!(state, Ok(RunState::Breakpoint(6)));
+        assert_eq!(prg.program_counter(), 6);
+        assert_eq!(prg.peek(9), 1);
+        assert!(output.is_empty());
+
+        let state = prg.run_to_breakpoint(|| 8, |val| output.push(val));
+        assert_eq!(state, Ok(RunState::Halted));
+        assert_eq!(output, vec![1]);
+    }
+
+    #[test]
+    fn breakpoint_at_start() {
+        let mut prg = Program::from_str("3,9,8,9,10,9,4,9,99,-1,8");
+        prg.add_breakpoint(0);
+
+        let state = prg.run_to_breakpoint(|| 8, |_| ());
+        assert_eq!(state, Ok(RunState::Breakpoint(0)));
+        assert_eq!(prg.program_counter(), 0);
+
+        let state = prg.run_to_breakpoint(|| 8, |_| ());
+        assert_eq!(state, Ok(RunState::Halted));
+    }
+
+    #[test]
+    fn breakpoint_off_end() {
+        // Add 1 to address 0 and run off the end of memory.
+        let mut prg = Program::from_str("1001,0,1,0");
+        let state = prg.run_to_breakpoint(|| 0, |_| ());
+        assert_eq!(state, Err(ExecutionError::PcOutOfBounds(4)));
+    }
+
+    #[test]
+    fn opcode_counts() {
+        // The day 9 quine outputs each of its 16 values in turn.
+        let mut prg =
+            Program::from_str("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+        prg.run_with_limit(1000, || 0, |_| ()).unwrap();
+
+        let counts = prg.opcode_counts();
+        assert_eq!(counts["BASE"], 16);
+        assert_eq!(counts["OUT"], 16);
+        assert_eq!(counts["ADD"], 16);
+        assert_eq!(counts["EQ"], 16);
+        assert_eq!(counts["JIF"], 16);
+        assert_eq!(counts["HALT"], 1);
+        assert_eq!(counts.get("IN"), None);
+        assert_eq!(counts.values().sum::<u64>(), prg.steps_executed());
+
+        prg.reset();
+        assert!(prg.opcode_counts().is_empty());
+
+        // The counts are indexed by operation.
+        for (i, &op) in OPERATIONS.iter().enumerate() {
+            assert_eq!(op as usize, i);
+        }
+    }
+
+    #[test]
+    fn fluent_config() {
+        // Output forever.
+        let mut prg = Program::from_str("104,1,1105,1,0");
+        prg.with_step_limit(5)
+            .with_checked_arithmetic(true)
+            .with_loop_detection(true);
+        assert_eq!(
+            prg.run_with_limit(100, || 0, |_| ()),
+            Err(ExecutionError::StepLimitExceeded)
+        );
+        assert_eq!(prg.steps_executed(), 5);
+
+        let mut prg = Program::from_str("1101,9223372036854775807,1,0,99");
+        prg.with_checked_arithmetic(true).with_history(1);
+        assert_eq!(
+            prg.step(&mut || 0, &mut |_| ()),
+            Err(ExecutionError::ArithmeticOverflow(0))
+        );
+        assert_eq!(prg.steps_executed(), 0);
+
+        let mut prg = Program::from_str("1105,1,0");
+        prg.with_step_limit(100).with_loop_detection(true);
+        assert_eq!(
+            prg.run_with_limit(100, || 0, |_| ()),
+            Err(ExecutionError::InfiniteLoop(0))
+        );
+    }
+
+    #[test]
+    fn write_watch() {
+        let mut prg = Program::from_str("1101,2,3,0,3,7,99,0");
+        prg.run_with_limit(10, || 5, |_| ()).unwrap();
+        assert!(prg.write_log().is_empty());
+
+        prg.reset();
+        prg.enable_write_watch();
+        prg.poke(1, 4);
+        prg.run_with_limit(10, || 5, |_| ()).unwrap();
+        assert_eq!(prg.write_log(), &[(1, 2, 4), (0, 1101, 7), (7, 0, 5)]);
+
+        prg.reset();
+        assert!(prg.write_log().is_empty());
+    }
+
+    #[test]
+    fn pipe_feedback() {
+        // Amplifier feedback loop example from day 7.
+        let prg = Program::from_str(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        );
+        let programs: Vec<Program> = [9, 8, 7, 6, 5]
+            .iter()
+            .map(|&phase| {
+                let mut amp = prg.clone();
+                amp.push_input(phase);
+                amp
+            })
+            .collect();
+
+        let output = pipe(programs, vec![0]).unwrap();
+        assert_eq!(output.last(), Some(&139629729));
+    }
+
+    #[test]
+    fn write_counts() {
+        // Increment address 100 forever, for ten iterations of the loop.
+        let mut prg = Program::from_str("1001,100,1,100,1105,1,0");
+        prg.enable_write_counts();
+        prg.with_step_limit(20);
+        assert_eq!(
+            prg.run_with_limit(100, || 0, |_| ()),
+            Err(ExecutionError::StepLimitExceeded)
+        );
+        assert_eq!(prg.write_count(100), 10);
+        assert_eq!(prg.write_count(0), 0);
+
+        prg.reset();
+        assert_eq!(prg.write_count(100), 0);
+    }
+
+    #[test]
+    fn validate_modes() {
+        let prg = Program::from_str("1101,1,2,0,99");
+        assert_eq!(prg.validate_modes(), Ok(()));
+
+        let prg = Program::from_str("3,0,11101,1,2,0,99");
+        assert_eq!(
+            prg.validate_modes(),
+            Err(vec![(
+                2,
+                String::from("Immediate mode write: ADD [imm 1] [imm 2] -> [imm 0]")
+            )])
+        );
+
+        // Code after a HALT is still checked.
+        let prg = Program::from_str("99,3,0,11101,1,2,0,99");
+        assert_eq!(
+            prg.validate_modes(),
+            Err(vec![(
+                3,
+                String::from("Immediate mode write: ADD [imm 1] [imm 2] -> [imm 0]")
+            )])
+        );
+    }
+
+    #[test]
+    fn pipe_deadlock() {
+        // Both programs wait for input forever, so the ring can never make
+        // progress - this should return rather than hang.
+        let prg = Program::from_str("3,0,3,0,99");
+        assert_eq!(
+            pipe(vec![prg.clone(), prg], vec![]),
+            Err(ExecutionError::Deadlock)
+        );
+
+        // Each program adds one to its input and passes it on, then waits
+        // for another input. The first program halts after the second
+        // input, leaving the second program waiting after one round.
+        let first = Program::from_str("3,0,1001,0,1,0,4,0,3,0,99");
+        let second = Program::from_str("3,0,1001,0,1,0,4,0,3,0,3,0,99");
+        assert_eq!(
+            pipe(vec![first, second], vec![3]),
+            Err(ExecutionError::Deadlock)
+        );
+    }
+
+    #[test]
+    fn opcode_table() {
+        let info = opcode_info(1).unwrap();
+        assert_eq!(info.op, Operation::ADD);
+        assert_eq!(info.param_count, 3);
+        assert_eq!(info.write_param, Some(2));
+
+        let info = opcode_info(3).unwrap();
+        assert_eq!(info.op, Operation::IN);
+        assert_eq!(info.param_count, 1);
+        assert_eq!(info.write_param, Some(0));
+
+        let info = opcode_info(99).unwrap();
+        assert_eq!(info.op, Operation::HALT);
+        assert_eq!(info.param_count, 0);
+        assert_eq!(info.write_param, None);
+
+        assert_eq!(opcode_info(42), None);
+    }
+
     #[test]
     fn quine() {
         // Quine test from day 9 pt 1